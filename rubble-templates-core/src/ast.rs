@@ -2,13 +2,14 @@
 
 use std::fmt::{Display, Formatter};
 use crate::units::Position;
+use crate::value::Value;
 
 /// Represents a node in an AST
 ///
 /// Used to represent a template code for further evaluation.
 ///
 /// Example:
-/// `(plus 1 2)` can be represented as:
+/// `(plus 1 x)` can be represented as:
 /// ```text
 ///     AnonymousNode {
 ///         starts_at: 0,
@@ -17,13 +18,12 @@ use crate::units::Position;
 ///                  identifier: "plus".to_string(),
 ///                  starts_at: 1,
 ///                  children: vec![
-///                      NamedNode {
-///                          identifier: "1".to_string(),
+///                      Literal {
+///                          value: Literal::Integer(1),
 ///                          starts_at: 6,
-///                          children: vec![],
 ///                      },
 ///                      NamedNode {
-///                          identifier: "2".to_string(),
+///                          identifier: "x".to_string(),
 ///                          starts_at: 8,
 ///                          children: vec![],
 ///                      },
@@ -35,15 +35,54 @@ use crate::units::Position;
 ///
 #[derive(Clone, Debug, PartialEq)]
 pub enum SyntaxNode {
+    /// An identifier (a variable or a function name), with arguments if it is a function call.
     NamedNode {
         identifier: String,
         starts_at: Position,
         children: Vec<SyntaxNode>,
     },
+    /// A group of nodes, eg. an expression in parenthesis.
     AnonymousNode {
         starts_at: Position,
         children: Vec<SyntaxNode>,
     },
+    /// A literal value, eg. `"text"`, `12`, `1.5` or `true`.
+    Literal {
+        value: Literal,
+        starts_at: Position,
+    },
+}
+
+/// A literal value in code.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    /// A string literal with escapes already replaced, eg. `"a\"b"` is `a"b`.
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+}
+
+impl From<&Literal> for Value {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::String(value) => Value::String(value.clone()),
+            Literal::Integer(value) => Value::Integer(*value),
+            Literal::Float(value) => Value::Float(*value),
+            Literal::Boolean(value) => Value::Bool(*value),
+        }
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::String(value) => write!(f, "{:?}", value),
+            Literal::Integer(value) => write!(f, "{}", value),
+            Literal::Float(value) => write!(f, "{:?}", value),
+            Literal::Boolean(value) => write!(f, "{}", value),
+        }
+    }
 }
 
 impl SyntaxNode {
@@ -51,11 +90,24 @@ impl SyntaxNode {
         matches!(*self, SyntaxNode::AnonymousNode { .. })
     }
 
+    pub fn starts_at(&self) -> &Position {
+        match self {
+            SyntaxNode::AnonymousNode { starts_at, .. } => starts_at,
+            SyntaxNode::NamedNode { starts_at, .. } => starts_at,
+            SyntaxNode::Literal { starts_at, .. } => starts_at,
+        }
+    }
+
+    /// Adds a child to the node. Literals cannot have children, so a literal is grouped with the child in an anonymous node.
     pub fn add_child(self, child: SyntaxNode) -> SyntaxNode {
         let mut parent = self;
         match parent {
             SyntaxNode::AnonymousNode { ref mut children, .. } => children.push(child),
             SyntaxNode::NamedNode { ref mut children, .. } => children.push(child),
+            SyntaxNode::Literal { ref starts_at, .. } => return SyntaxNode::AnonymousNode {
+                starts_at: starts_at.clone(),
+                children: vec![parent, child],
+            },
         };
         parent
     }
@@ -75,6 +127,13 @@ impl SyntaxNode {
                     children,
                     starts_at: identifier_starts_at,
                 },
+
+            SyntaxNode::Literal { .. } =>
+                SyntaxNode::NamedNode {
+                    identifier: new_identifier.to_string(),
+                    children: vec![],
+                    starts_at: identifier_starts_at,
+                },
         }
     }
 }
@@ -85,7 +144,58 @@ impl Display for SyntaxNode {
             SyntaxNode::AnonymousNode { children, starts_at } =>
                 write!(f, "AnonymousNode at {} ({} children)", starts_at, children.len()),
             SyntaxNode::NamedNode { identifier, children, starts_at} =>
-                write!(f, "SyntaxNode \"{}\" at {} ({} children)", identifier, starts_at, children.len())
+                write!(f, "SyntaxNode \"{}\" at {} ({} children)", identifier, starts_at, children.len()),
+            SyntaxNode::Literal { value, starts_at } =>
+                write!(f, "Literal {} at {}", value, starts_at),
+        }
+    }
+}
+/// An error that can happen while parsing code into an AST.
+///
+/// Contains the position of the offending character in the code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub position: Position,
+    pub kind: ParseErrorKind,
+}
+
+impl ParseError {
+    pub fn new(position: Position, kind: ParseErrorKind) -> ParseError {
+        ParseError { position, kind }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseErrorKind {
+    /// Used when a `(` is never closed.
+    UnclosedParenthesis,
+    /// Used when a `)` does not close any `(`.
+    UnexpectedParenthesis,
+    /// Used when a string literal is not terminated with `"`, points at the opening quote.
+    UnterminatedString,
+    /// Used when there is nothing in parenthesis, eg. `()`.
+    EmptyCall,
+    /// Used when a pipe (`|`) has no expression on the left or no function name on the right.
+    MisplacedPipe,
+    /// Used when an integer literal does not fit in 64 bits, eg. `9223372036854775808`.
+    IntegerOutOfRange,
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::UnclosedParenthesis => write!(f, "unclosed `(`"),
+            ParseErrorKind::UnexpectedParenthesis => write!(f, "unexpected `)`"),
+            ParseErrorKind::UnterminatedString => write!(f, "unterminated string literal"),
+            ParseErrorKind::EmptyCall => write!(f, "empty call `()`"),
+            ParseErrorKind::MisplacedPipe => write!(f, "`|` has to be between an expression and a function name"),
+            ParseErrorKind::IntegerOutOfRange => write!(f, "integer literal is out of range, quote it to use it as text"),
         }
     }
-}
\ No newline at end of file
+}
@@ -2,9 +2,10 @@
 
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use crate::ast::ParseError;
 use crate::evaluator::{SyntaxError, Context};
 use crate::template::EvaluableMixedContent;
-use crate::units::Position;
+use crate::units::{Location, Position};
 
 /// Describes a struct that is able to compile a template.
 ///
@@ -24,19 +25,214 @@ pub trait Compiler<T> {
         where C: EvaluableMixedContent<Item=Self::Item, IntoIter=Self::ItemIterator>;
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum CompilationError {
+    /// Used when code fails to evaluate. The error is boxed, as it is much larger than other variants.
     EvaluationFailed {
-        error: SyntaxError,
+        error: Box<SyntaxError>,
+        position: Position,
+        source: String,
+    },
+    /// Used when code of a block is malformed, eg. has unbalanced parenthesis.
+    ParseFailed {
+        error: ParseError,
         position: Position,
         source: String,
     },
+    /// Used when a code block has no end delimiter, eg. `{{` without `}}`. Points at the start delimiter.
+    ///
+    /// The source contains the beginning of the block, up to the end of its line.
+    UnterminatedBlock {
+        position: Position,
+        source: String,
+    },
+    /// Used when a block (eg. `{{ if }}`) is opened, but never closed. Points at the opening tag.
+    UnclosedBlock {
+        tag: String,
+        position: Position,
+        source: String,
+    },
+    /// Used when a block tag (eg. `{{ else }}` or `{{ end }}`) does not match the currently opened block.
+    ///
+    /// Contains the position of the opening tag of the current block, if there is any.
+    UnexpectedBlockTag {
+        tag: String,
+        position: Position,
+        source: String,
+        opened_at: Option<Position>,
+    },
+    /// Used when a compiler continues past failing blocks, contains all errors that were found.
+    ///
+    /// If the compiler was configured to return partial output, it contains the output with failed blocks replaced.
+    Multiple {
+        errors: Vec<CompilationError>,
+        output: Option<String>,
+    },
+}
+
+impl CompilationError {
+    /// Returns the absolute offset (in bytes) of the error in template, if it is known.
+    ///
+    /// Points at the offending symbol when its position is known, otherwise at the code block where the error occurred.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            CompilationError::EvaluationFailed { error, position, .. } =>
+                absolute(&error.relative_pos)
+                    .or_else(|| absolute(&error.invocation_pos))
+                    .or_else(|| absolute(position)),
+            CompilationError::ParseFailed { error, position, .. } =>
+                absolute(&error.position).or_else(|| absolute(position)),
+            CompilationError::UnterminatedBlock { position, .. } => absolute(position),
+            CompilationError::UnclosedBlock { position, .. } => absolute(position),
+            CompilationError::UnexpectedBlockTag { position, .. } => absolute(position),
+            CompilationError::Multiple { errors, .. } => errors.first().and_then(|error| error.offset()),
+        }
+    }
+
+    fn describe(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompilationError::EvaluationFailed { error, .. } =>
+                write!(f, "{}", error),
+            CompilationError::ParseFailed { error, .. } =>
+                write!(f, "{}", error),
+            CompilationError::UnterminatedBlock { .. } =>
+                write!(f, "code block has no end delimiter"),
+            CompilationError::UnclosedBlock { tag, .. } =>
+                write!(f, "`{}` block is never closed", tag),
+            CompilationError::UnexpectedBlockTag { tag, opened_at: Some(_), .. } =>
+                write!(f, "unexpected `{}` tag in the current block", tag),
+            CompilationError::UnexpectedBlockTag { tag, opened_at: None, .. } =>
+                write!(f, "unexpected `{}` tag outside of a block", tag),
+            CompilationError::Multiple { errors, .. } =>
+                write!(f, "found {} error(s)", errors.len()),
+        }
+    }
+}
+
+fn absolute(position: &Position) -> Option<usize> {
+    match position {
+        Position::Absolute(offset) => Some(*offset),
+        _ => None,
+    }
 }
 
 impl Error for CompilationError {}
 
 impl Display for CompilationError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        self.describe(f)?;
+        match self {
+            CompilationError::Multiple { errors, .. } => {
+                for error in errors {
+                    write!(f, "\n{}", error)?;
+                }
+            }
+            _ => if let Some(offset) = self.offset() {
+                write!(f, " (at offset {})", offset)?;
+            },
+        }
+        Ok(())
+    }
+}
+
+/// A [CompilationError] located in the template it was returned for.
+///
+/// When displayed, it shows the template name (if it is known), the line and column of the error
+/// and the line of template with a caret under the offending symbol:
+/// ```
+/// use rubble_templates_core::compiler::{CompilationError, TemplateError};
+/// use rubble_templates_core::evaluator::{EvaluationError, SyntaxError};
+/// use rubble_templates_core::units::Position;
+///
+/// let source = "Hello,\nthere {{ name }}!";
+/// let error = CompilationError::EvaluationFailed {
+///     error: Box::new(SyntaxError::at_position(Position::Absolute(16), EvaluationError::UnknownSymbol { symbol: "name".to_string() })),
+///     position: Position::Absolute(13),
+///     source: "{{ name }}".to_string(),
+/// };
+///
+/// let error = TemplateError::new(error, source, Some("greeting.txt"));
+///
+/// assert_eq!(error.to_string(), "\
+/// unknown symbol `name`
+///  --> greeting.txt:2:10
+///   |
+/// 2 | there {{ name }}!
+///   |          ^");
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct TemplateError {
+    pub error: CompilationError,
+    /// Name of the template (eg. a file name), if it is known.
+    pub name: Option<String>,
+    /// Location of the error, if its offset is known.
+    pub location: Option<Location>,
+    /// The line of template where the error is located.
+    pub snippet: Option<String>,
+    /// Located errors of [CompilationError::Multiple].
+    pub causes: Vec<TemplateError>,
+}
+
+impl TemplateError {
+    /// Locates the error in the template source.
+    ///
+    /// Errors contained in [CompilationError::Multiple] are located separately, as `causes`.
+    pub fn new(error: CompilationError, source: &str, name: Option<&str>) -> TemplateError {
+        if let CompilationError::Multiple { errors, output } = error {
+            return TemplateError {
+                causes: errors.iter()
+                    .map(|error| TemplateError::new(error.clone(), source, name))
+                    .collect(),
+                error: CompilationError::Multiple { errors, output },
+                name: name.map(|name| name.to_string()),
+                location: None,
+                snippet: None,
+            };
+        }
+
+        let location = error.offset().and_then(|offset| Location::of(source, offset));
+        let snippet = location.and_then(|location| source.lines().nth(location.line - 1))
+            .map(|line| line.trim_end_matches('\r').to_string());
+
+        TemplateError {
+            error,
+            name: name.map(|name| name.to_string()),
+            location,
+            snippet,
+            causes: vec![],
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.error.describe(f)?;
+
+        match (&self.name, &self.location) {
+            (Some(name), Some(location)) => write!(f, "\n --> {}:{}", name, location)?,
+            (Some(name), None) if self.causes.is_empty() => write!(f, "\n --> {}", name)?,
+            (None, Some(location)) => write!(f, "\n --> {}", location)?,
+            _ => {}
+        }
+
+        if let (Some(location), Some(snippet)) = (&self.location, &self.snippet) {
+            let number = location.line.to_string();
+            let margin = " ".repeat(number.len());
+            // tabs are kept, so the caret is aligned with the snippet
+            let indent: String = snippet.chars()
+                .take(location.column - 1)
+                .map(|char| if char == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            write!(f, "\n{} |\n{} | {}\n{} | {}^", margin, number, snippet, margin, indent)?;
+        }
+
+        for cause in &self.causes {
+            write!(f, "\n\n{}", cause)?;
+        }
+
+        Ok(())
     }
 }
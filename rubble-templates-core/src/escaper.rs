@@ -0,0 +1,23 @@
+//! An API for escaping of values emitted by compilers.
+
+/// Describes a struct that is able to escape text, so it can be safely used in the compiled output (eg. HTML or JSON).
+///
+/// Compilers use escapers to convert results of code evaluation before they are emitted.
+/// This trait is implemented for all `Fn(&str) -> String` closures and functions:
+///
+/// ```
+/// use rubble_templates_core::escaper::Escaper;
+///
+/// let escaper = |text: &str| text.replace('\n', "\\n");
+///
+/// assert_eq!(escaper.escape("a\nb"), "a\\nb");
+/// ```
+pub trait Escaper {
+    fn escape(&self, text: &str) -> String;
+}
+
+impl<F> Escaper for F where F: Fn(&str) -> String {
+    fn escape(&self, text: &str) -> String {
+        self(text)
+    }
+}
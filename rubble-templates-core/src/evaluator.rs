@@ -2,8 +2,12 @@
 
 use std::collections::HashMap;
 use std::any::{TypeId, Any};
+use std::fmt::{Display, Formatter};
 use crate::ast::SyntaxNode;
 use crate::units::Position;
+use crate::value::Value;
+#[cfg(feature = "serde")]
+use crate::value::ser::SerializationError;
 
 /// Trait that describes an ability to evaluate code in template.
 ///
@@ -11,7 +15,7 @@ use crate::units::Position;
 /// AST are not constructed by the [Evaluator], they are consumed by it.
 ///
 pub trait Evaluator {
-    fn evaluate(&self, syntax_node: &SyntaxNode, context: &mut Context) -> Result<String, SyntaxError>;
+    fn evaluate(&self, syntax_node: &SyntaxNode, context: &mut Context) -> Result<Value, SyntaxError>;
 }
 
 /// Context that is passed while evaluating an AST by an [Evaluator].
@@ -19,12 +23,17 @@ pub trait Evaluator {
 /// Contains all variables and states that can be shared between functions during evaluations.
 /// Functions should be free to store any state that will be shared between function invocations.
 ///
-/// Variables are stored in a map, keys are Strings and values are also Strings.
+/// Variables are stored in a map, keys are Strings and values are [Value]s.
 /// State is stored in a heterogeneous container, which means that is accepts any struct.
 /// Structs in this store are identifier by their [TypeId].
 ///
+/// Variables can be also stored in nested scopes (eg. for loop variables).
+/// A variable set in a scope shadows variables with the same name from outer scopes
+/// and it is removed when the scope is popped.
+///
 pub struct Context {
-    variables: HashMap<String, String>,
+    variables: HashMap<String, Value>,
+    scopes: Vec<HashMap<String, Value>>,
     states: HashMap<TypeId, Box<dyn Any>>,
 }
 
@@ -32,23 +41,95 @@ impl Context {
     pub fn empty() -> Context {
         Context {
             variables: HashMap::new(),
+            scopes: vec![],
             states: HashMap::new(),
         }
     }
 
-    pub fn with_variables(variables: HashMap<String, String>) -> Context {
+    pub fn with_variables<V: Into<Value>>(variables: HashMap<String, V>) -> Context {
         Context {
-            variables,
+            variables: variables.into_iter()
+                .map(|(name, value)| (name, value.into()))
+                .collect(),
+            scopes: vec![],
             states: HashMap::new(),
         }
     }
 
-    pub fn set_variable(&mut self, name: &str, value: &str) {
-        self.variables.insert(name.to_string(), value.to_string());
+    /// Creates a context with variables taken from any [Serialize](serde::Serialize) implementation.
+    ///
+    /// The value has to be serialized as a map (eg. a struct or a `HashMap`), its entries become variables.
+    /// Nested structs, enums, vectors and maps are converted to [Value]s (see [Value::from_serialize]).
+    ///
+    /// Example:
+    /// ```
+    /// use serde::Serialize;
+    /// use rubble_templates_core::evaluator::Context;
+    /// use rubble_templates_core::value::Value;
+    ///
+    /// #[derive(Serialize)]
+    /// struct User {
+    ///     name: String,
+    ///     roles: Vec<String>,
+    /// }
+    ///
+    /// let user = User { name: "Joe".to_string(), roles: vec!["admin".to_string()] };
+    /// let context = Context::from_serialize(&user).unwrap();
+    ///
+    /// assert_eq!(context.get_variable("name"), Some(&Value::from("Joe")));
+    /// assert_eq!(context.get_variable("roles"), Some(&Value::from(vec!["admin"])));
+    /// assert!(Context::from_serialize(&vec![1, 2]).is_err());
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn from_serialize<T: serde::Serialize + ?Sized>(value: &T) -> Result<Context, SerializationError> {
+        match Value::from_serialize(value)? {
+            Value::Map(variables) => Ok(Context {
+                variables: variables.into_iter().collect(),
+                scopes: vec![],
+                states: HashMap::new(),
+            }),
+            other => Err(SerializationError::new(
+                &format!("only structs and maps can be used as variables, got {}", other.type_name())
+            )),
+        }
+    }
+
+    /// Sets a variable in the innermost scope.
+    pub fn set_variable<V: Into<Value>>(&mut self, name: &str, value: V) {
+        let variables = self.scopes.last_mut().unwrap_or(&mut self.variables);
+        variables.insert(name.to_string(), value.into());
     }
 
-    pub fn get_variable(&self, name: &str) -> Option<&String> {
-        self.variables.get(name)
+    /// Looks up a variable, starting from the innermost scope.
+    pub fn get_variable(&self, name: &str) -> Option<&Value> {
+        self.scopes.iter().rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.variables.get(name))
+    }
+
+    /// Starts a new nested scope for variables.
+    ///
+    /// ```
+    /// use rubble_templates_core::evaluator::Context;
+    /// use rubble_templates_core::value::Value;
+    ///
+    /// let mut context = Context::empty();
+    /// context.set_variable("item", "outer");
+    ///
+    /// context.push_scope();
+    /// context.set_variable("item", "inner");
+    /// assert_eq!(context.get_variable("item"), Some(&Value::from("inner")));
+    ///
+    /// context.pop_scope();
+    /// assert_eq!(context.get_variable("item"), Some(&Value::from("outer")));
+    /// ```
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    /// Removes the innermost scope with all its variables.
+    pub fn pop_scope(&mut self) {
+        self.scopes.pop();
     }
 
     pub fn save_state<T: Any>(&mut self, state: T) {
@@ -75,7 +156,7 @@ impl Context {
 /// Unlike [EvaluationError], it can be created where a much broader context is available
 /// and when additional info can be supplied (eg. position of currently evaluated block).
 ///
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SyntaxError {
     pub relative_pos: Position,
     pub invocation_pos: Position,
@@ -115,7 +196,7 @@ impl SyntaxError {
 /// to be able to create an [SyntaxError] if it happens. It contains only the most necessary
 /// info about the error.
 ///
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum EvaluationError {
     UnexpectedElements {
         last_expected: Option<SyntaxNode>,
@@ -124,16 +205,56 @@ pub enum EvaluationError {
     UnknownSymbol {
         symbol: String,
     },
+    /// Used when a variable exists, but a segment of the accessed path (eg. `city` in `user.address.city`) does not.
+    UnknownPathSegment {
+        path: String,
+        segment: String,
+    },
     InvalidArguments {
         description: Option<String>,
         arguments: Vec<SyntaxNode>,
     },
     InvalidValues {
         description: Option<String>,
-        values: Vec<String>,
+        values: Vec<Value>,
     },
 }
 
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+/// Describes the error in a human readable way, eg. ``unknown symbol `name` ``.
+impl Display for EvaluationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvaluationError::UnexpectedElements { unexpected_elements, .. } =>
+                write!(f, "unexpected elements: {}", join(unexpected_elements)),
+            EvaluationError::UnknownSymbol { symbol } =>
+                write!(f, "unknown symbol `{}`", symbol),
+            EvaluationError::UnknownPathSegment { path, segment } =>
+                write!(f, "unknown segment `{}` of `{}`", segment, path),
+            EvaluationError::InvalidArguments { description: Some(description), .. } =>
+                write!(f, "invalid arguments: {}", description),
+            EvaluationError::InvalidArguments { description: None, arguments } =>
+                write!(f, "invalid arguments: {}", join(arguments)),
+            EvaluationError::InvalidValues { description: Some(description), .. } =>
+                write!(f, "invalid values: {}", description),
+            EvaluationError::InvalidValues { description: None, values } =>
+                write!(f, "invalid values: {}", join(values)),
+        }
+    }
+}
+
+fn join<T: Display>(items: &[T]) -> String {
+    items.iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 /// A function that can be used to add features to the template.
 ///
 /// Any struct that implements this trait adds additional features that can additional *function*
@@ -145,12 +266,12 @@ pub enum EvaluationError {
 /// Parameter evaluation with a supplied Evaluator is optional and a given [Function] can evaluate
 /// them independently.
 pub trait Function {
-    fn evaluate(&self, evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<String, SyntaxError>;
+    fn evaluate(&self, evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<Value, SyntaxError>;
 }
 
 /// Impl for [Function] that allows to use lambda as a function in [Evaluator].
 ///
-/// Allows to use `Fn(&dyn Evaluator, &[SyntaxNode], &mut Context) -> Result<String, SyntaxError>` as [Function] in [Evaluator].
+/// Allows to use `Fn(&dyn Evaluator, &[SyntaxNode], &mut Context) -> Result<Value, SyntaxError>` as [Function] in [Evaluator].
 /// For other implementations, see [crate::functions].
 ///
 /// Example:
@@ -158,23 +279,23 @@ pub trait Function {
 /// use rubble_templates_core::evaluator::{Evaluator, Function, SyntaxError, Context};
 /// use std::collections::HashMap;
 /// use rubble_templates_core::ast::SyntaxNode;
+/// use rubble_templates_core::value::Value;
 ///
-/// fn plus_function(evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<String, SyntaxError> {
-///     Ok(
+/// fn plus_function(evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<Value, SyntaxError> {
+///     Ok(Value::Integer(
 ///         parameters.iter()
 ///             .map(|node|
-///                 evaluator.evaluate(node, context).unwrap().parse::<i32>().unwrap()
+///                 evaluator.evaluate(node, context).unwrap().as_i64().unwrap()
 ///             )
-///             .sum::<i32>()
-///             .to_string()
-///     )
+///             .sum::<i64>()
+///     ))
 /// }
 ///
 /// let mut functions: HashMap<String, Box<dyn Function>> = HashMap::new();
 /// functions.insert("plus".to_string(), Box::new(plus_function)); // will be treated as Box<dyn Function>
 /// ```
-impl<F> Function for F where F: Fn(&dyn Evaluator, &[SyntaxNode], &mut Context) -> Result<String, SyntaxError> {
-    fn evaluate(&self, evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<String, SyntaxError> {
-        self(evaluator, &parameters, context)
+impl<F> Function for F where F: Fn(&dyn Evaluator, &[SyntaxNode], &mut Context) -> Result<Value, SyntaxError> {
+    fn evaluate(&self, evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<Value, SyntaxError> {
+        self(evaluator, parameters, context)
     }
 }
\ No newline at end of file
@@ -18,14 +18,16 @@
 //! use std::collections::HashMap;
 //! use rubble_templates_core::functions::SimpleFunction;
 //! use rubble_templates_core::evaluator::Function;
+//! use rubble_templates_core::value::Value;
 //!
-//! fn plus_function(parameters: &[String]) -> String {
-//!     parameters.iter()
-//!          .map(|param|
-//!              param.parse::<i32>().unwrap()
-//!          )
-//!          .sum::<i32>()
-//!          .to_string()
+//! fn plus_function(parameters: &[Value]) -> Value {
+//!     Value::Integer(
+//!         parameters.iter()
+//!             .map(|param|
+//!                 param.as_i64().unwrap()
+//!             )
+//!             .sum::<i64>()
+//!     )
 //! }
 //!
 //! let mut functions: HashMap<String, Box<dyn Function>> = HashMap::new();
@@ -37,8 +39,9 @@
 
 use crate::evaluator::{Function, Evaluator, SyntaxError, Context};
 use crate::ast::SyntaxNode;
+use crate::value::Value;
 
-/// A wrapper for a `Fn(&[String]) -> String`, to be used in [Evaluator].
+/// A wrapper for a `Fn(&[Value]) -> Value`, to be used in [Evaluator].
 ///
 ///
 /// Example:
@@ -46,31 +49,33 @@ use crate::ast::SyntaxNode;
 /// use rubble_templates_core::evaluator::{Evaluator, Function, SyntaxError};
 /// use std::collections::HashMap;
 /// use rubble_templates_core::functions::SimpleFunction;
+/// use rubble_templates_core::value::Value;
 ///
-/// fn plus_function(parameters: &[String]) -> String {
-///     parameters.iter()
+/// fn plus_function(parameters: &[Value]) -> Value {
+///     Value::Integer(
+///         parameters.iter()
 ///             .map(|param|
-///                 param.parse::<i32>().unwrap()
+///                 param.as_i64().unwrap()
 ///             )
-///             .sum::<i32>()
-///             .to_string()
+///             .sum::<i64>()
+///     )
 /// }
 ///
 /// let mut functions: HashMap<String, Box<dyn Function>> = HashMap::new();
 /// functions.insert("plus".to_string(), SimpleFunction::new(plus_function)); // will be treated as Box<dyn Function>
 /// ```
-pub struct SimpleFunction<F> where F: Fn(&[String]) -> String {
+pub struct SimpleFunction<F> where F: Fn(&[Value]) -> Value {
     function: F
 }
 
-impl<F> SimpleFunction<F> where F: Fn(&[String]) -> String {
+impl<F> SimpleFunction<F> where F: Fn(&[Value]) -> Value {
     pub fn new(function: F) -> Box<SimpleFunction<F>> {
         Box::new(SimpleFunction { function })
     }
 }
 
-impl<F> Function for SimpleFunction<F> where F: Fn(&[String]) -> String {
-    fn evaluate(&self, evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<String, SyntaxError> {
+impl<F> Function for SimpleFunction<F> where F: Fn(&[Value]) -> Value {
+    fn evaluate(&self, evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<Value, SyntaxError> {
         let parameters = resolve_params(evaluator, parameters, context);
         match parameters {
             Ok(parameters) => Ok((self.function)(&parameters)),
@@ -80,41 +85,41 @@ impl<F> Function for SimpleFunction<F> where F: Fn(&[String]) -> String {
 }
 
 
-/// A wrapper for a `Fn(&dyn Evaluator, &[String], &mut Context) -> Result<String, SyntaxError>`, to be used in [Evaluator].
+/// A wrapper for a `Fn(&[Value], &mut Context) -> Result<Value, SyntaxError>`, to be used in [Evaluator].
 ///
 /// Example:
 /// ```
 /// use std::collections::HashMap;
 /// use rubble_templates_core::evaluator::{Context, SyntaxError, Function};
 /// use rubble_templates_core::functions::FunctionWithContext;
+/// use rubble_templates_core::value::Value;
 ///
-/// fn plus_function(parameters: &[String], _context: &mut Context) -> Result<String, SyntaxError> {
-///     Ok(
+/// fn plus_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+///     Ok(Value::Integer(
 ///         parameters.iter()
 ///             .map(|param|
-///                 param.parse::<i32>().unwrap()
+///                 param.as_i64().unwrap()
 ///             )
-///             .sum::<i32>()
-///             .to_string()
-///     )
+///             .sum::<i64>()
+///     ))
 /// }
 ///
 /// let mut functions: HashMap<String, Box<dyn Function>> = HashMap::new();
 /// functions.insert("plus".to_string(), FunctionWithContext::new(plus_function)); // will be treated as Box<dyn Function>
 ///
 /// ```
-pub struct FunctionWithContext<F> where F: Fn(&[String], &mut Context) -> Result<String, SyntaxError> {
+pub struct FunctionWithContext<F> where F: Fn(&[Value], &mut Context) -> Result<Value, SyntaxError> {
     function: F
 }
 
-impl<F> FunctionWithContext<F> where F: Fn(&[String], &mut Context) -> Result<String, SyntaxError> {
+impl<F> FunctionWithContext<F> where F: Fn(&[Value], &mut Context) -> Result<Value, SyntaxError> {
     pub fn new(function: F) -> Box<FunctionWithContext<F>> {
         Box::new(FunctionWithContext { function })
     }
 }
 
-impl<F> Function for FunctionWithContext<F> where F: Fn(&[String], &mut Context) -> Result<String, SyntaxError> {
-    fn evaluate(&self, evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<String, SyntaxError> {
+impl<F> Function for FunctionWithContext<F> where F: Fn(&[Value], &mut Context) -> Result<Value, SyntaxError> {
+    fn evaluate(&self, evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<Value, SyntaxError> {
         let parameters = resolve_params(evaluator, parameters, context);
         match parameters {
             Ok(parameters) => (self.function)(&parameters, context),
@@ -124,7 +129,7 @@ impl<F> Function for FunctionWithContext<F> where F: Fn(&[String], &mut Context)
 }
 
 
-/// A wrapper for a `Fn(&dyn Evaluator, &[SyntaxNode], &mut Context) -> Result<String, SyntaxError>`, to be used in [Evaluator].
+/// A wrapper for a `Fn(&dyn Evaluator, &[SyntaxNode], &mut Context) -> Result<Value, SyntaxError>`, to be used in [Evaluator].
 ///
 /// Example:
 /// ```
@@ -132,46 +137,46 @@ impl<F> Function for FunctionWithContext<F> where F: Fn(&[String], &mut Context)
 /// use std::collections::HashMap;
 /// use rubble_templates_core::ast::SyntaxNode;
 /// use rubble_templates_core::functions::FunctionWithAst;
+/// use rubble_templates_core::value::Value;
 ///
-/// fn plus_function(evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<String, SyntaxError> {
-///     Ok(
+/// fn plus_function(evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<Value, SyntaxError> {
+///     Ok(Value::Integer(
 ///         parameters.iter()
 ///             .map(|node|
-///                 evaluator.evaluate(node, context).unwrap().parse::<i32>().unwrap()
+///                 evaluator.evaluate(node, context).unwrap().as_i64().unwrap()
 ///             )
-///             .sum::<i32>()
-///             .to_string()
-///     )
+///             .sum::<i64>()
+///     ))
 /// }
 ///
 /// let mut functions: HashMap<String, Box<dyn Function>> = HashMap::new();
 /// functions.insert("plus".to_string(), FunctionWithAst::new(plus_function)); // will be treated as Box<dyn Function>
 ///
 /// ```
-pub struct FunctionWithAst<F> where F: Fn(&dyn Evaluator, &[SyntaxNode], &mut Context) -> Result<String, SyntaxError> {
+pub struct FunctionWithAst<F> where F: Fn(&dyn Evaluator, &[SyntaxNode], &mut Context) -> Result<Value, SyntaxError> {
     function: F
 }
 
-impl<F> FunctionWithAst<F> where F: Fn(&dyn Evaluator, &[SyntaxNode], &mut Context) -> Result<String, SyntaxError> {
+impl<F> FunctionWithAst<F> where F: Fn(&dyn Evaluator, &[SyntaxNode], &mut Context) -> Result<Value, SyntaxError> {
     pub fn new(function: F) -> Box<FunctionWithAst<F>> {
         Box::new(FunctionWithAst { function })
     }
 }
 
-impl<F> Function for FunctionWithAst<F> where F: Fn(&dyn Evaluator, &[SyntaxNode], &mut Context) -> Result<String, SyntaxError> {
-    fn evaluate(&self, evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<String, SyntaxError> {
-        (self.function)(evaluator, &parameters, context)
+impl<F> Function for FunctionWithAst<F> where F: Fn(&dyn Evaluator, &[SyntaxNode], &mut Context) -> Result<Value, SyntaxError> {
+    fn evaluate(&self, evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<Value, SyntaxError> {
+        (self.function)(evaluator, parameters, context)
     }
 }
 
-/// Resolves a slice of [SyntaxNode]s to a `Vec` of strings.
+/// Resolves a slice of [SyntaxNode]s to a `Vec` of values.
 ///
-/// Invokes Evaluator on each [SyntaxNode] and returns a `Result` containing a `Vec` of strings (baked parameters ready to use)
+/// Invokes Evaluator on each [SyntaxNode] and returns a `Result` containing a `Vec` of [Value]s (baked parameters ready to use)
 /// or a [SyntaxError] if any parameter evaluation fails.
-pub fn resolve_params(evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<Vec<String>, SyntaxError> {
+pub fn resolve_params(evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<Vec<Value>, SyntaxError> {
     parameters.iter()
         .map(|parameter| {
             evaluator.evaluate(parameter, context)
         })
-        .collect::<Result<Vec<String>, SyntaxError>>()
+        .collect::<Result<Vec<Value>, SyntaxError>>()
 }
\ No newline at end of file
@@ -1,5 +1,7 @@
 pub mod units;
 pub mod template;
+pub mod value;
+pub mod escaper;
 
 #[cfg(feature = "ast")]
 pub mod ast;
@@ -25,4 +25,16 @@ pub enum TemplateSlice<'a> {
         start_position: usize,
         end_position: usize,
     },
+    /// A comment that is not evaluated and produces no output, the value includes the delimiters.
+    Comment {
+        value: &'a str,
+        start_position: usize,
+        end_position: usize,
+    },
+    /// A code block (or a comment) without the end delimiter, the value contains the rest of the template.
+    Unterminated {
+        value: &'a str,
+        start_position: usize,
+        end_position: usize,
+    },
 }
@@ -24,10 +24,79 @@ impl Position {
             Position::Absolute(pos) => Some(pos),
         }.cloned()
     }
+
+    /// Returns the same kind of position moved forward by a given offset.
+    pub fn shifted(&self, offset: usize) -> Position {
+        match self {
+            Position::Unknown => Position::Unknown,
+            Position::RelativeToInvocation(pos) => Position::RelativeToInvocation(pos + offset),
+            Position::RelativeToCodeStart(pos) => Position::RelativeToCodeStart(pos + offset),
+            Position::Absolute(pos) => Position::Absolute(pos + offset),
+        }
+    }
 }
 
 impl Display for Position {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
     }
-}
\ No newline at end of file
+}
+
+/// Represents a fragment of template, from the `start` offset (inclusive) to the `end` offset (exclusive).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// Represents a line and a column in template, both starting from 1.
+///
+/// Columns are counted in characters, so they point at the same place as seen in a text editor.
+///
+/// ```
+/// use rubble_templates_core::units::Location;
+///
+/// let source = "Hello,\nżółw {{ name }}!";
+///
+/// assert_eq!(Location::of(source, 0), Some(Location { line: 1, column: 1 }));
+/// assert_eq!(Location::of(source, 15), Some(Location { line: 2, column: 6 }));
+/// assert_eq!(Location::of(source, 100), None);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// Finds the location of a byte offset in the source text.
+    ///
+    /// Returns `None` if the offset is beyond the end of text or it is not at a character boundary.
+    pub fn of(source: &str, offset: usize) -> Option<Location> {
+        let before = source.get(..offset)?;
+        let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+
+        Some(Location {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        })
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
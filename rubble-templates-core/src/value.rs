@@ -0,0 +1,280 @@
+//! Typed values that are used in variables, function results and evaluation.
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[cfg(feature = "serde")]
+pub mod ser;
+
+/// Represents a value that can be stored in a [Context](crate::evaluator::Context) or returned by an evaluation.
+///
+/// Values are converted to text only when the compiler writes the output,
+/// so numbers, booleans, lists and maps can be passed between functions without re-parsing them.
+///
+/// Example:
+/// ```
+/// use rubble_templates_core::value::Value;
+///
+/// let number = Value::from(42);
+/// let list = Value::from(vec![1, 2, 3]);
+///
+/// assert_eq!(number.as_i64(), Some(42));
+/// assert_eq!(list.to_string(), "[1, 2, 3]");
+/// ```
+#[derive(Clone, Debug, PartialEq, Default)]
+pub enum Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    /// A string that is marked as safe, it is emitted as it is even if the compiler escapes the output.
+    Safe(String),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(*self, Value::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns a floating point representation of this value if it is a number ([Value::Integer] or [Value::Float]).
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(value) => Some(*value as f64),
+            Value::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns a string slice if this value is a string ([Value::String] or [Value::Safe]).
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) | Value::Safe(value) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::List(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&BTreeMap<String, Value>> {
+        match self {
+            Value::Map(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Checks whether this value is considered true in conditions.
+    ///
+    /// Null, `false`, zero (and NaN), empty strings, empty lists and empty maps are false,
+    /// all other values are true.
+    ///
+    /// ```
+    /// use rubble_templates_core::value::Value;
+    ///
+    /// assert!(Value::from("text").is_truthy());
+    /// assert!(!Value::from("").is_truthy());
+    /// assert!(!Value::Integer(0).is_truthy());
+    /// assert!(!Value::from(Vec::<String>::new()).is_truthy());
+    /// ```
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(value) => *value,
+            Value::Integer(value) => *value != 0,
+            Value::Float(value) => *value != 0.0 && !value.is_nan(),
+            Value::String(value) | Value::Safe(value) => !value.is_empty(),
+            Value::List(values) => !values.is_empty(),
+            Value::Map(values) => !values.is_empty(),
+        }
+    }
+
+    /// Returns a name of the type of this value, useful for error descriptions.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Integer(_) => "integer",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::Safe(_) => "safe string",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+        }
+    }
+}
+
+/// Converts a value to text, as it will appear in the compiled template.
+///
+/// [Value::Null] is an empty text, lists are written as `[a, b]` and maps as `{key: value}`.
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::String(value) | Value::Safe(value) => write!(f, "{}", value),
+            Value::List(values) => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(values) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Integer(value as i64)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+/// Returned when an unsigned integer does not fit in [Value::Integer], eg. `u64::MAX`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IntegerRangeError {
+    pub value: u64,
+}
+
+impl Error for IntegerRangeError {}
+
+impl Display for IntegerRangeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is out of the supported integer range", self.value)
+    }
+}
+
+/// Values above `i64::MAX` are rejected instead of wrapping to negative numbers.
+impl TryFrom<u64> for Value {
+    type Error = IntegerRangeError;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        i64::try_from(value)
+            .map(Value::Integer)
+            .map_err(|_| IntegerRangeError { value })
+    }
+}
+
+/// Values above `i64::MAX` are rejected instead of wrapping to negative numbers.
+impl TryFrom<usize> for Value {
+    type Error = IntegerRangeError;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        Value::try_from(value as u64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<&String> for Value {
+    fn from(value: &String) -> Self {
+        Value::String(value.clone())
+    }
+}
+
+impl<T> From<Option<T>> for Value where T: Into<Value> {
+    fn from(value: Option<T>) -> Self {
+        value.map(|it| it.into()).unwrap_or(Value::Null)
+    }
+}
+
+impl<T> From<Vec<T>> for Value where T: Into<Value> {
+    fn from(values: Vec<T>) -> Self {
+        Value::List(values.into_iter().map(|it| it.into()).collect())
+    }
+}
+
+impl<T> From<BTreeMap<String, T>> for Value where T: Into<Value> {
+    fn from(values: BTreeMap<String, T>) -> Self {
+        Value::Map(values.into_iter().map(|(key, value)| (key, value.into())).collect())
+    }
+}
+
+impl<T> From<HashMap<String, T>> for Value where T: Into<Value> {
+    fn from(values: HashMap<String, T>) -> Self {
+        Value::Map(values.into_iter().map(|(key, value)| (key, value.into())).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use crate::value::{IntegerRangeError, Value};
+
+    #[test]
+    fn should_convert_unsigned_integers_in_range() {
+        assert_eq!(Value::try_from(42_usize), Ok(Value::Integer(42)));
+        assert_eq!(Value::try_from(i64::MAX as u64), Ok(Value::Integer(i64::MAX)));
+        assert_eq!(Value::try_from(i64::MAX as u64 + 1), Err(IntegerRangeError { value: i64::MAX as u64 + 1 }));
+        assert_eq!(
+            Value::try_from(u64::MAX).map_err(|error| error.to_string()),
+            Err("18446744073709551615 is out of the supported integer range".to_string())
+        );
+    }
+}
@@ -0,0 +1,575 @@
+//! Conversion of any [Serialize] implementation into a [Value].
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use serde::ser::{self, Serialize, Impossible};
+use crate::value::Value;
+
+/// An error that can happen during conversion of a [Serialize] implementation into a [Value].
+///
+/// Indicates that the serialized data has a shape that cannot be represented by a [Value],
+/// eg. a map with non-string keys or a 128-bit integer.
+#[derive(Debug, PartialEq)]
+pub struct SerializationError {
+    pub description: String,
+}
+
+impl SerializationError {
+    pub fn new(description: &str) -> SerializationError {
+        SerializationError {
+            description: description.to_string(),
+        }
+    }
+}
+
+impl Error for SerializationError {}
+
+impl Display for SerializationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot convert to value: {}", self.description)
+    }
+}
+
+impl ser::Error for SerializationError {
+    fn custom<T: Display>(msg: T) -> Self {
+        SerializationError::new(&msg.to_string())
+    }
+}
+
+impl Value {
+    /// Converts any [Serialize] implementation into a [Value].
+    ///
+    /// Structs and maps are converted to [Value::Map], sequences and tuples to [Value::List].
+    /// Enum variants with data are represented as a map with a single entry (`{variant: data}`),
+    /// and unit variants as a string with the variant name.
+    ///
+    /// Example:
+    /// ```
+    /// use serde::Serialize;
+    /// use rubble_templates_core::value::Value;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Point {
+    ///     x: i32,
+    ///     y: i32,
+    /// }
+    ///
+    /// let value = Value::from_serialize(&Point { x: 1, y: 2 }).unwrap();
+    ///
+    /// assert_eq!(value.to_string(), "{x: 1, y: 2}");
+    /// ```
+    pub fn from_serialize<T: Serialize + ?Sized>(value: &T) -> Result<Value, SerializationError> {
+        value.serialize(ValueSerializer)
+    }
+}
+
+/// A [serde::Serializer] that produces a [Value].
+pub struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = SerializationError;
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeVariant<SerializeList>;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeVariant<SerializeMap>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, SerializationError> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, SerializationError> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, SerializationError> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, SerializationError> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, SerializationError> {
+        Ok(Value::Integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, SerializationError> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, SerializationError> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, SerializationError> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, SerializationError> {
+        Value::try_from(v).map_err(|error| SerializationError::new(&error.to_string()))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, SerializationError> {
+        Ok(Value::Float(v as f64))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, SerializationError> {
+        Ok(Value::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, SerializationError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, SerializationError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, SerializationError> {
+        Ok(Value::List(v.iter().map(|byte| Value::Integer(*byte as i64)).collect()))
+    }
+
+    fn serialize_none(self) -> Result<Value, SerializationError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, SerializationError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, SerializationError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, SerializationError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<Value, SerializationError> {
+        Ok(Value::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<Value, SerializationError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _variant_index: u32, variant: &'static str, value: &T) -> Result<Value, SerializationError> {
+        let mut map = BTreeMap::new();
+        map.insert(variant.to_string(), value.serialize(self)?);
+        Ok(Value::Map(map))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList, SerializationError> {
+        Ok(SerializeList {
+            values: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList, SerializationError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeList, SerializationError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, len: usize) -> Result<SerializeVariant<SerializeList>, SerializationError> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, SerializationError> {
+        Ok(SerializeMap {
+            values: BTreeMap::new(),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, SerializationError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, len: usize) -> Result<SerializeVariant<SerializeMap>, SerializationError> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+pub struct SerializeList {
+    values: Vec<Value>,
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializationError> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, SerializationError> {
+        Ok(Value::List(self.values))
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializationError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, SerializationError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializationError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, SerializationError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+pub struct SerializeMap {
+    values: BTreeMap<String, Value>,
+    next_key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializationError> {
+        self.next_key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializationError> {
+        let key = self.next_key.take()
+            .ok_or_else(|| SerializationError::new("map value serialized before its key"))?;
+        self.values.insert(key, value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, SerializationError> {
+        Ok(Value::Map(self.values))
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), SerializationError> {
+        self.values.insert(key.to_string(), value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, SerializationError> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// Wraps a serialized enum variant content into a map with a single entry (`{variant: content}`).
+pub struct SerializeVariant<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<S> SerializeVariant<S> {
+    fn wrap(variant: &'static str, value: Value) -> Value {
+        let mut map = BTreeMap::new();
+        map.insert(variant.to_string(), value);
+        Value::Map(map)
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeList> {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializationError> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Value, SerializationError> {
+        let value = ser::SerializeSeq::end(self.inner)?;
+        Ok(Self::wrap(self.variant, value))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeMap> {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), SerializationError> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Value, SerializationError> {
+        let value = ser::SerializeMap::end(self.inner)?;
+        Ok(Self::wrap(self.variant, value))
+    }
+}
+
+/// A [serde::Serializer] for map keys, accepts only keys that can be represented as text.
+struct KeySerializer;
+
+impl KeySerializer {
+    fn unsupported(kind: &str) -> SerializationError {
+        SerializationError::new(&format!("map keys must be strings, numbers or booleans, got {}", kind))
+    }
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = SerializationError;
+    type SerializeSeq = Impossible<String, SerializationError>;
+    type SerializeTuple = Impossible<String, SerializationError>;
+    type SerializeTupleStruct = Impossible<String, SerializationError>;
+    type SerializeTupleVariant = Impossible<String, SerializationError>;
+    type SerializeMap = Impossible<String, SerializationError>;
+    type SerializeStruct = Impossible<String, SerializationError>;
+    type SerializeStructVariant = Impossible<String, SerializationError>;
+
+    fn serialize_bool(self, v: bool) -> Result<String, SerializationError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, SerializationError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, SerializationError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, SerializationError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, SerializationError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, SerializationError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, SerializationError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, SerializationError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, SerializationError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, SerializationError> {
+        Err(Self::unsupported("a float"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, SerializationError> {
+        Err(Self::unsupported("a float"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, SerializationError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, SerializationError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, SerializationError> {
+        Err(Self::unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<String, SerializationError> {
+        Err(Self::unsupported("none"))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, SerializationError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, SerializationError> {
+        Err(Self::unsupported("a unit"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, SerializationError> {
+        Err(Self::unsupported("a unit struct"))
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<String, SerializationError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<String, SerializationError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _value: &T) -> Result<String, SerializationError> {
+        Err(Self::unsupported("an enum variant with data"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerializationError> {
+        Err(Self::unsupported("a sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerializationError> {
+        Err(Self::unsupported("a tuple"))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, SerializationError> {
+        Err(Self::unsupported("a tuple struct"))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, SerializationError> {
+        Err(Self::unsupported("an enum variant with data"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerializationError> {
+        Err(Self::unsupported("a map"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, SerializationError> {
+        Err(Self::unsupported("a struct"))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, SerializationError> {
+        Err(Self::unsupported("an enum variant with data"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use serde::{Serialize, Serializer};
+    use serde::ser::SerializeMap;
+    use crate::evaluator::Context;
+    use crate::value::Value;
+    use crate::value::ser::SerializationError;
+
+    #[derive(Serialize)]
+    enum Shape {
+        Empty,
+        Circle(u32),
+        Line(i32, i32),
+        Rectangle { width: u32, height: u32 },
+    }
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(entries.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+
+    /// A map with a single entry, so keys of any type can be serialized (eg. floats that cannot be `HashMap` keys).
+    struct SingleEntry<K>(K);
+
+    impl<K: Serialize> Serialize for SingleEntry<K> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut map = serializer.serialize_map(Some(1))?;
+            map.serialize_entry(&self.0, &true)?;
+            map.end()
+        }
+    }
+
+    fn error(description: &str) -> Result<Value, SerializationError> {
+        Err(SerializationError::new(description))
+    }
+
+    #[test]
+    fn should_convert_enum_variants() {
+        assert_eq!(Value::from_serialize(&Shape::Empty), Ok(Value::from("Empty")));
+        assert_eq!(Value::from_serialize(&Shape::Circle(2)), Ok(map(vec![("Circle", Value::Integer(2))])));
+        assert_eq!(
+            Value::from_serialize(&Shape::Line(1, -1)),
+            Ok(map(vec![("Line", Value::List(vec![Value::Integer(1), Value::Integer(-1)]))]))
+        );
+        assert_eq!(
+            Value::from_serialize(&Shape::Rectangle { width: 2, height: 3 }),
+            Ok(map(vec![("Rectangle", map(vec![("height", Value::Integer(3)), ("width", Value::Integer(2))]))]))
+        );
+    }
+
+    #[test]
+    fn should_convert_options_and_units() {
+        #[derive(Serialize)]
+        struct Unit;
+
+        #[derive(Serialize)]
+        struct Id(u32);
+
+        assert_eq!(Value::from_serialize(&None::<i32>), Ok(Value::Null));
+        assert_eq!(Value::from_serialize(&Some(1)), Ok(Value::Integer(1)));
+        assert_eq!(Value::from_serialize(&()), Ok(Value::Null));
+        assert_eq!(Value::from_serialize(&Unit), Ok(Value::Null));
+        assert_eq!(Value::from_serialize(&Id(7)), Ok(Value::Integer(7)));
+        assert_eq!(Value::from_serialize(&vec![Some("a"), None]), Ok(Value::List(vec![Value::from("a"), Value::Null])));
+    }
+
+    #[test]
+    fn should_reject_integers_out_of_range() {
+        assert_eq!(Value::from_serialize(&(i64::MAX as u64)), Ok(Value::Integer(i64::MAX)));
+        assert_eq!(Value::from_serialize(&(i64::MAX as u64 + 1)), error("9223372036854775808 is out of the supported integer range"));
+        assert_eq!(Value::from_serialize(&u64::MAX), error("18446744073709551615 is out of the supported integer range"));
+        assert!(Value::from_serialize(&1_i128).is_err());
+    }
+
+    #[test]
+    fn should_convert_map_keys_to_text() {
+        let mut values = BTreeMap::new();
+        values.insert(1, "a");
+        values.insert(2, "b");
+        assert_eq!(Value::from_serialize(&values), Ok(map(vec![("1", Value::from("a")), ("2", Value::from("b"))])));
+
+        assert_eq!(Value::from_serialize(&SingleEntry(Shape::Empty)), Ok(map(vec![("Empty", Value::Bool(true))])));
+        assert_eq!(Value::from_serialize(&SingleEntry(Some('c'))), Ok(map(vec![("c", Value::Bool(true))])));
+    }
+
+    #[test]
+    fn should_reject_unsupported_map_keys() {
+        let expected = |kind: &str| error(&format!("map keys must be strings, numbers or booleans, got {}", kind));
+        assert_eq!(Value::from_serialize(&SingleEntry(1.5_f64)), expected("a float"));
+        assert_eq!(Value::from_serialize(&SingleEntry(None::<String>)), expected("none"));
+        assert_eq!(Value::from_serialize(&SingleEntry(())), expected("a unit"));
+        assert_eq!(Value::from_serialize(&SingleEntry(vec![1])), expected("a sequence"));
+        assert_eq!(Value::from_serialize(&SingleEntry((1, 2))), expected("a tuple"));
+        assert_eq!(Value::from_serialize(&SingleEntry(Shape::Circle(1))), expected("an enum variant with data"));
+        assert_eq!(Value::from_serialize(&SingleEntry(Shape::Line(1, 2))), expected("an enum variant with data"));
+        assert_eq!(Value::from_serialize(&SingleEntry(Shape::Rectangle { width: 1, height: 1 })), expected("an enum variant with data"));
+    }
+
+    #[test]
+    fn should_reject_variables_that_are_not_maps() {
+        let expected = |kind: &str| Err(SerializationError::new(&format!("only structs and maps can be used as variables, got {}", kind)));
+
+        assert_eq!(Context::from_serialize(&vec![1, 2]).map(|_| ()), expected("list"));
+        assert_eq!(Context::from_serialize(&1).map(|_| ()), expected("integer"));
+        assert_eq!(Context::from_serialize(&Shape::Empty).map(|_| ()), expected("string"));
+        assert_eq!(Context::from_serialize(&None::<i32>).map(|_| ()), expected("null"));
+        assert!(Context::from_serialize(&Shape::Circle(1)).is_ok());
+    }
+}
@@ -0,0 +1,258 @@
+//! Lexer that splits code into tokens for the [parser](crate::parser)
+//!
+//! Tokens are separated by whitespace (any Unicode whitespace, eg. spaces, tabs and line breaks),
+//! parenthesis and pipes. Spans of tokens are byte offsets in the code, so they can be used to slice it.
+
+use rubble_templates_core::ast::{ParseError, ParseErrorKind};
+use rubble_templates_core::units::{Position, Span};
+
+/// Kind of a [Token].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TokenKind {
+    /// `(`
+    LeftParenthesis,
+    /// `)`
+    RightParenthesis,
+    /// `|`
+    Pipe,
+    /// An identifier, a number or a boolean.
+    ///
+    /// A word can contain string literals, eg. a path `map["key with space"]`.
+    /// These literals are kept as they are written in the code, with their quotes and escapes,
+    /// so offsets in the word match the code, eg. `map["a\"b"]` is `map["a\"b"]`.
+    Word(String),
+    /// A string literal without quotes, with escapes already replaced, eg. `"a\"b"` is `a"b`.
+    String(String),
+}
+
+/// A token with its span in the code.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Splits code into [Token]s.
+///
+/// A string literal is a separate token when it starts a word, otherwise it is a part of the word.
+/// String literals can contain reserved characters and the following escapes: `\"`, `\\`, `\n`, `\r`, `\t`
+/// and `\u{..}` (a hexadecimal Unicode code point). Unknown escapes are kept as they are.
+///
+/// ```
+/// use rubble_templates_core::units::Span;
+/// use rubble_templates_evaluators::lexer::{tokenize, Token, TokenKind};
+///
+/// let tokens = tokenize("żółw\t(\"a b\")").unwrap();
+///
+/// assert_eq!(tokens, vec![
+///     Token { kind: TokenKind::Word("żółw".to_string()), span: Span::new(0, 7) },
+///     Token { kind: TokenKind::LeftParenthesis, span: Span::new(8, 9) },
+///     Token { kind: TokenKind::String("a b".to_string()), span: Span::new(9, 14) },
+///     Token { kind: TokenKind::RightParenthesis, span: Span::new(14, 15) },
+/// ]);
+/// ```
+pub fn tokenize(code: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = vec![];
+    let mut word = String::new();
+    let mut word_start = 0;
+    let mut string_start: Option<usize> = None;
+    let mut escape: Option<String> = None;
+    let mut raw_escape = false;
+
+    for (index, char) in code.char_indices() {
+        if string_start == Some(word_start) {
+            if !push_string_char(&mut word, char, &mut escape) {
+                let literal = std::mem::take(&mut word);
+                tokens.push(Token {
+                    kind: TokenKind::String(literal[1..(literal.len() - 1)].to_string()),
+                    span: Span::new(word_start, index + 1),
+                });
+                string_start = None;
+            }
+            continue;
+        }
+        if string_start.is_some() {
+            word.push(char);
+            if raw_escape {
+                raw_escape = false;
+            } else if char == '\\' {
+                raw_escape = true;
+            } else if char == '"' {
+                string_start = None;
+            }
+            continue;
+        }
+
+        let separator = match char {
+            '(' => Some(TokenKind::LeftParenthesis),
+            ')' => Some(TokenKind::RightParenthesis),
+            '|' => Some(TokenKind::Pipe),
+            _ if char.is_whitespace() => None,
+            _ => {
+                if word.is_empty() {
+                    word_start = index;
+                }
+                if char == '"' {
+                    string_start = Some(index);
+                }
+                word.push(char);
+                continue;
+            }
+        };
+
+        if !word.is_empty() {
+            tokens.push(Token {
+                kind: TokenKind::Word(std::mem::take(&mut word)),
+                span: Span::new(word_start, index),
+            });
+        }
+        if let Some(kind) = separator {
+            tokens.push(Token {
+                kind,
+                span: Span::new(index, index + char.len_utf8()),
+            });
+        }
+    }
+
+    if let Some(start) = string_start {
+        return Err(ParseError::new(Position::RelativeToCodeStart(start), ParseErrorKind::UnterminatedString));
+    }
+    if !word.is_empty() {
+        tokens.push(Token {
+            kind: TokenKind::Word(word),
+            span: Span::new(word_start, code.len()),
+        });
+    }
+
+    Ok(tokens)
+}
+
+/// Replaces escapes in the content of a string literal (without quotes) the same way [tokenize] does.
+pub(crate) fn unescape_string(content: &str) -> String {
+    let mut unescaped = String::new();
+    let mut escape = None;
+    for char in content.chars() {
+        push_string_char(&mut unescaped, char, &mut escape);
+    }
+    if let Some(sequence) = escape {
+        unescaped.push_str(&sequence);
+    }
+    unescaped
+}
+
+/// Adds a character of a string literal to the word, returns `false` if the character closes the literal.
+fn push_string_char(word: &mut String, char: char, escape: &mut Option<String>) -> bool {
+    if let Some(mut sequence) = escape.take() {
+        sequence.push(char);
+        match unescape(&sequence) {
+            Escape::Complete(unescaped) => word.push(unescaped),
+            Escape::Pending => *escape = Some(sequence),
+            Escape::Invalid => {
+                // keep the unknown escape as it is and scan the last character again
+                word.push_str(&sequence[..sequence.len() - char.len_utf8()]);
+                return push_string_char(word, char, escape);
+            }
+        }
+        true
+    } else if char == '\\' {
+        *escape = Some(char.to_string());
+        true
+    } else {
+        word.push(char);
+        char != '"'
+    }
+}
+
+enum Escape {
+    Complete(char),
+    Pending,
+    Invalid,
+}
+
+fn unescape(escape: &str) -> Escape {
+    match escape {
+        "\\\"" => Escape::Complete('"'),
+        "\\\\" => Escape::Complete('\\'),
+        "\\n" => Escape::Complete('\n'),
+        "\\r" => Escape::Complete('\r'),
+        "\\t" => Escape::Complete('\t'),
+        "\\u" | "\\u{" => Escape::Pending,
+        _ => match escape.strip_prefix("\\u{") {
+            Some(code) if code.ends_with('}') => u32::from_str_radix(&code[..code.len() - 1], 16).ok()
+                .and_then(char::from_u32)
+                .map(Escape::Complete)
+                .unwrap_or(Escape::Invalid),
+            Some(code) if code.len() <= 6 && code.chars().all(|it| it.is_ascii_hexdigit()) => Escape::Pending,
+            _ => Escape::Invalid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rubble_templates_core::ast::{ParseError, ParseErrorKind};
+    use rubble_templates_core::units::{Position, Span};
+    use crate::lexer::{tokenize, unescape_string, Token, TokenKind};
+
+    fn word(value: &str, start: usize, end: usize) -> Token {
+        Token {
+            kind: TokenKind::Word(value.to_string()),
+            span: Span::new(start, end),
+        }
+    }
+
+    #[test]
+    fn should_split_on_unicode_whitespace() {
+        let tokens = tokenize(" a\tbc\n\u{3000}d\u{a0}é|f").unwrap();
+
+        assert_eq!(tokens, vec![
+            word("a", 1, 2),
+            word("bc", 3, 5),
+            word("d", 9, 10),
+            word("é", 12, 14),
+            Token { kind: TokenKind::Pipe, span: Span::new(14, 15) },
+            word("f", 15, 16),
+        ]);
+    }
+
+    #[test]
+    fn should_keep_string_literals_in_one_token() {
+        let tokens = tokenize(r#"concat "ż (|) \"\u{17C}\q" x"#).unwrap();
+
+        assert_eq!(tokens, vec![
+            word("concat", 0, 6),
+            Token { kind: TokenKind::String("ż (|) \"ż\\q".to_string()), span: Span::new(7, 27) },
+            word("x", 28, 29),
+        ]);
+    }
+
+    #[test]
+    fn should_keep_string_literals_inside_words() {
+        let tokens = tokenize(r#"map["a b"].c "d"e"#).unwrap();
+
+        assert_eq!(tokens, vec![
+            word("map[\"a b\"].c", 0, 12),
+            Token { kind: TokenKind::String("d".to_string()), span: Span::new(13, 16) },
+            word("e", 16, 17),
+        ]);
+    }
+
+    #[test]
+    fn should_keep_escapes_in_string_literals_inside_words() {
+        let tokens = tokenize(r#"map["a\"b\\"].c x"#).unwrap();
+
+        assert_eq!(tokens, vec![
+            word(r#"map["a\"b\\"].c"#, 0, 15),
+            word("x", 16, 17),
+        ]);
+        assert_eq!(unescape_string(r#"a\"b\\\u{17C}\q\u{1"#), "a\"b\\ż\\q\\u{1");
+    }
+
+    #[test]
+    fn should_report_unterminated_string() {
+        assert_eq!(
+            tokenize("ż \"a\\\""),
+            Err(ParseError::new(Position::RelativeToCodeStart(3), ParseErrorKind::UnterminatedString))
+        );
+    }
+}
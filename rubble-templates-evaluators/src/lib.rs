@@ -1,3 +1,4 @@
+pub mod lexer;
 pub mod parser;
 #[cfg(feature = "simple")]
 pub mod simple;
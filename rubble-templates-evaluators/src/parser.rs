@@ -1,8 +1,9 @@
 //! Default parser logic
 
-use log::{debug, trace, log_enabled, Level};
-use rubble_templates_core::ast::SyntaxNode;
+use log::{debug, log_enabled, Level};
+use rubble_templates_core::ast::{SyntaxNode, ParseError, ParseErrorKind, Literal};
 use rubble_templates_core::units::Position;
+use crate::lexer::{tokenize, Token, TokenKind};
 
 /// Used for parsing AST for further evaluation.
 ///
@@ -11,145 +12,245 @@ use rubble_templates_core::units::Position;
 ///
 /// It also allows to use parenthesis to evaluate a nested function.
 ///
+/// Code is split into tokens by the [lexer](crate::lexer). Positions of nodes are byte offsets in the code.
+///
 /// Reserved characters (cannot be used in names):
-/// * ` ` - space
+/// * whitespace (eg. space, tab or line break)
 /// * `(` - left parenthesis
 /// * `)` - right parenthesis
+/// * `|` - pipe
+///
+/// Reserved characters can be used in string literals, eg. `"(a b)"`.
+/// String literals also support the following escapes: `\"`, `\\`, `\n`, `\r`, `\t` and `\u{..}` (a hexadecimal Unicode code point).
+/// Other backslashes are kept as they are.
 ///
 /// Examples:
 /// * `(function 1 2 3)` - interpreted as `function` call with parameters `1`, `2` and `3`
 /// * `plus 1 2 (times 3 4)` - interpreted as `1 + 2 + (3 * 4)`, given `plus` is an addition function and `times` is a multiplication function
 ///
-pub fn parse_ast(source: &str, code_start: &str, code_end: &str) -> SyntaxNode {
+/// Function calls can also be chained with pipes, the result of the expression on the left is passed
+/// as the first parameter to the function on the right (see [desugar_pipes]):
+/// * `name | trim | upper` - interpreted as `upper (trim name)`
+/// * `concat a b | replace "x" "y"` - interpreted as `replace (concat a b) "x" "y"`
+///
+/// Malformed code results in a [ParseError] (see [ParseErrorKind]) that points at the offending character, eg.:
+/// * `plus (times 1 2` - unclosed `(`,
+/// * `plus 1 2)` - unexpected `)`,
+/// * `concat "text` - unterminated string literal,
+/// * `plus () 1` - empty call,
+/// * `name | (trim)` - a pipe without a function name.
+///
+/// ```
+/// use rubble_templates_core::ast::{ParseError, ParseErrorKind};
+/// use rubble_templates_core::units::Position;
+/// use rubble_templates_evaluators::parser::parse_ast;
+///
+/// assert!(parse_ast("{{ plus 1 (times 2 3) }}", "{{", "}}").is_ok());
+/// assert_eq!(
+///     parse_ast("{{ plus 1 (times 2 3 }}", "{{", "}}"),
+///     Err(ParseError::new(Position::RelativeToCodeStart(8), ParseErrorKind::UnclosedParenthesis))
+/// );
+/// ```
+pub fn parse_ast(source: &str, code_start: &str, code_end: &str) -> Result<SyntaxNode, ParseError> {
+    parse_ast_with_pipes(source, code_start, code_end).map(desugar_pipes)
+}
+
+/// Parses AST like [parse_ast], but leaves pipes (`|`) as nodes, so they can be desugared later with [desugar_pipes].
+pub(crate) fn parse_ast_with_pipes(source: &str, code_start: &str, code_end: &str) -> Result<SyntaxNode, ParseError> {
     if log_enabled!(Level::Debug) {
         debug!("Starting to parse AST of: {}", source);
     }
     let source = source.strip_prefix(code_start).unwrap_or(source);
     let source = source.strip_suffix(code_end).unwrap_or(source);
 
-    let result = next_node_of(source, 0, 0).0;
+    let mut tokens = tokenize(source)?.into_iter();
+    let result = parse_sequence(&mut tokens, None)?;
     if log_enabled!(Level::Debug) {
         debug!("Parsing complete, result: {:?}", result);
     }
-    result
+    Ok(result)
 }
 
-struct SyntaxScanResult(SyntaxNode, usize);
-
-fn next_node_of(source: &str, offset: usize, level: usize) -> SyntaxScanResult {
-    if log_enabled!(Level::Trace) {
-        trace!("{:->width$}>{}", "", source, width = level);
-    }
-    let mut syntax_node = SyntaxNode::AnonymousNode {
-        children: vec![],
-        starts_at: Position::RelativeToCodeStart(offset),
-    };
-    let mut identifier = "".to_string();
-    let mut string_started = false;
-    let mut identifier_start: usize = offset;
-    let mut skip_end: usize = 0;
-    let mut source_length: usize = 0;
-
-    for (index, char) in source.chars().enumerate() {
-        let position = offset + index;
-        source_length += 1;
-        if position <= skip_end {
-            continue;
-        }
-        let current_offset = index + 1;
-
-        let (id, started) = extract_string(identifier, char, string_started);
-        identifier = id;
-        string_started = started;
-        if string_started {
-            continue;
-        }
+/// Parses tokens until the end of code or until the `)` that closes the parenthesis opened at the given position.
+///
+/// A sequence that starts with a name is a function call (or a variable), eg. `f a b` is a `f` node with `a` and `b` children.
+/// Other sequences are anonymous nodes.
+fn parse_sequence<I>(tokens: &mut I, opened_at: Option<usize>) -> Result<SyntaxNode, ParseError>
+    where I: Iterator<Item=Token> {
+    let error = |position: usize, kind: ParseErrorKind| Err(ParseError::new(Position::RelativeToCodeStart(position), kind));
+    let mut children = vec![];
+    let mut starts_with_name = false;
+    // a pipe that still waits for a function name
+    let mut pipe: Option<usize> = None;
+    let mut empty_segment = true;
 
-        if char == '(' {
-            let (new_node, skip_pos) = start_node(syntax_node, &identifier, &source[current_offset..], identifier_start + 1, position, level);
-            syntax_node = new_node;
-            skip_end = skip_pos;
-        } else {
-            if char == ' ' || char == ')' {
-                syntax_node = add_identifier_or_child(
-                    syntax_node,
-                    &identifier,
-                    identifier_start + 1,
-                    level,
-                );
-                identifier.clear();
-                identifier_start = position + 1;
-            } else {
-                identifier.push(char);
+    while let Some(Token { kind, span }) = tokens.next() {
+        match (kind, pipe) {
+            (TokenKind::LeftParenthesis | TokenKind::RightParenthesis | TokenKind::Pipe, Some(pipe)) =>
+                return error(pipe, ParseErrorKind::MisplacedPipe),
+            (TokenKind::LeftParenthesis, None) => {
+                children.push(parse_sequence(tokens, Some(span.start))?);
+                empty_segment = false;
             }
-
-            if char == ')' {
-                if log_enabled!(Level::Trace) {
-                    trace!("{:->width$}Parsing of the following fragment is complete (finished at {}): {}, result: {:?}", "", position, source, syntax_node, width = level);
+            (TokenKind::RightParenthesis, None) => return match opened_at {
+                None => error(span.start, ParseErrorKind::UnexpectedParenthesis),
+                Some(opened_at) if children.is_empty() => error(opened_at, ParseErrorKind::EmptyCall),
+                Some(opened_at) => Ok(sequence_of(children, starts_with_name, opened_at)),
+            },
+            (TokenKind::Pipe, None) if empty_segment => return error(span.start, ParseErrorKind::MisplacedPipe),
+            (TokenKind::Pipe, None) => {
+                children.push(SyntaxNode::NamedNode {
+                    identifier: PIPE.to_string(),
+                    starts_at: Position::RelativeToCodeStart(span.start),
+                    children: vec![],
+                });
+                pipe = Some(span.start);
+                empty_segment = true;
+            }
+            (TokenKind::String(_), Some(pipe)) => return error(pipe, ParseErrorKind::MisplacedPipe),
+            (TokenKind::String(value), None) => {
+                children.push(SyntaxNode::Literal {
+                    value: Literal::String(value),
+                    starts_at: Position::RelativeToCodeStart(span.start),
+                });
+                empty_segment = false;
+            }
+            (TokenKind::Word(word), pending_pipe) => {
+                let starts_at = Position::RelativeToCodeStart(span.start);
+                let literal = match literal_of(&word) {
+                    Ok(literal) => literal,
+                    Err(kind) => return error(span.start, kind),
+                };
+                match (literal, pending_pipe) {
+                    (Some(_), Some(pending_pipe)) => return error(pending_pipe, ParseErrorKind::MisplacedPipe),
+                    (Some(value), None) => children.push(SyntaxNode::Literal { value, starts_at }),
+                    (None, _) => {
+                        starts_with_name = starts_with_name || children.is_empty();
+                        children.push(SyntaxNode::NamedNode {
+                            identifier: word,
+                            starts_at,
+                            children: vec![],
+                        });
+                    }
                 }
-                return SyntaxScanResult(syntax_node, position);
+                pipe = None;
+                empty_segment = false;
             }
         }
     }
 
-    let end_pos = offset + source_length;
-    if log_enabled!(Level::Trace) {
-        trace!("{:->width$}Parsing of the following fragment is complete (finished at {}): {}, result: {:?}", "", end_pos, source, syntax_node, width = level);
+    if let Some(pipe) = pipe {
+        return error(pipe, ParseErrorKind::MisplacedPipe);
+    }
+    match opened_at {
+        Some(opened_at) => error(opened_at, ParseErrorKind::UnclosedParenthesis),
+        None => Ok(sequence_of(children, starts_with_name, 0)),
     }
-    SyntaxScanResult(syntax_node, end_pos)
 }
 
-fn extract_string(identifier: String, char: char, string_started: bool) -> (String, bool) {
-    let mut identifier = identifier;
-    let mut string_started = string_started;
-
-    if string_started && char != '"' {
-        identifier.push(char);
-    } else if string_started {
-        string_started = false;
-    } else if char == '"' {
-        identifier.push(char);
-        string_started = true;
+/// Reads a number or a boolean literal.
+///
+/// Numbers have to start with a digit (optionally preceded by a sign), so eg. `inf` or `NaN` are identifiers.
+/// Words with only digits are integers, so they are never parsed as (imprecise) floats, even if they are out of range.
+fn literal_of(word: &str) -> Result<Option<Literal>, ParseErrorKind> {
+    match word {
+        "true" => return Ok(Some(Literal::Boolean(true))),
+        "false" => return Ok(Some(Literal::Boolean(false))),
+        _ => {}
     }
 
-    (identifier, string_started)
+    let digits = word.strip_prefix(['-', '+']).unwrap_or(word);
+    if !digits.starts_with(|it: char| it.is_ascii_digit()) {
+        Ok(None)
+    } else if digits.chars().all(|it| it.is_ascii_digit()) {
+        word.parse::<i64>()
+            .map(|value| Some(Literal::Integer(value)))
+            .map_err(|_| ParseErrorKind::IntegerOutOfRange)
+    } else {
+        Ok(word.parse::<f64>().ok().map(Literal::Float))
+    }
 }
 
-fn start_node(syntax_node: SyntaxNode, identifier: &str, source_remainder: &str, identifier_start: usize, position: usize, level: usize) -> (SyntaxNode, usize) {
-    let mut syntax_node = syntax_node;
-    syntax_node = add_identifier_or_child(
-        syntax_node,
-        identifier,
-        identifier_start,
-        level,
-    );
+fn sequence_of(children: Vec<SyntaxNode>, starts_with_name: bool, starts_at: usize) -> SyntaxNode {
+    let mut children = children.into_iter();
+    match children.next() {
+        Some(SyntaxNode::NamedNode { identifier, starts_at, .. }) if starts_with_name => SyntaxNode::NamedNode {
+            identifier,
+            starts_at,
+            children: children.collect(),
+        },
+        first => SyntaxNode::AnonymousNode {
+            starts_at: Position::RelativeToCodeStart(starts_at),
+            children: first.into_iter().chain(children).collect(),
+        },
+    }
+}
 
-    let SyntaxScanResult(child, skip_pos) = next_node_of(source_remainder, position, level + 1);
-    syntax_node = syntax_node.add_child(child);
+const PIPE: &str = "|";
 
-    (syntax_node, skip_pos)
-}
+/// Replaces pipes in a node (and its children) with function calls.
+///
+/// `x | f a b` is replaced with `f x a b`, where `x` can be any expression, eg. `g c | f a b` is replaced with `f (g c) a b`.
+/// The right side of a pipe has to start with a function name. Nodes with misplaced pipes are left unchanged.
+///
+/// ```
+/// use rubble_templates_core::ast::{SyntaxNode, ParseError, ParseErrorKind};
+/// use rubble_templates_evaluators::parser::{parse_ast, desugar_pipes};
+///
+/// let piped = parse_ast("{{ name | trim }}", "{{", "}}").unwrap();
+///
+/// if let SyntaxNode::NamedNode { identifier, children, .. } = &piped {
+///     assert_eq!(identifier, "trim");
+///     assert!(matches!(&children[..], [SyntaxNode::NamedNode { identifier, .. }] if identifier == "name"));
+/// } else {
+///     panic!("Expected a function call");
+/// }
+/// assert_eq!(desugar_pipes(piped.clone()), piped);
+/// ```
+pub fn desugar_pipes(node: SyntaxNode) -> SyntaxNode {
+    let (head, starts_at, children) = match node {
+        SyntaxNode::NamedNode { identifier, starts_at, children } => (Some(identifier), starts_at, children),
+        SyntaxNode::AnonymousNode { starts_at, children } => (None, starts_at, children),
+        SyntaxNode::Literal { .. } => return node,
+    };
+    let children: Vec<SyntaxNode> = children.into_iter().map(desugar_pipes).collect();
+    let rebuild = |head: Option<String>, starts_at: Position, children: Vec<SyntaxNode>| match head {
+        Some(identifier) => SyntaxNode::NamedNode { identifier, starts_at, children },
+        None => SyntaxNode::AnonymousNode { starts_at, children },
+    };
 
-fn add_identifier_or_child(syntax_node: SyntaxNode, new_identifier: &str, identifier_starts_at: usize, level: usize) -> SyntaxNode {
-    if new_identifier.is_empty() {
-        return syntax_node;
+    if !children.iter().any(is_pipe) {
+        return rebuild(head, starts_at, children);
     }
 
-    trace!("{:->width$}+\"{}\" at {}", "", new_identifier, identifier_starts_at, width = level);
-
-    if syntax_node.is_anonymous() {
-        syntax_node.with_identifier(new_identifier, Position::RelativeToCodeStart(identifier_starts_at))
+    let mut segments = children.split(is_pipe);
+    let first = segments.next().unwrap_or_default();
+    let calls: Vec<&[SyntaxNode]> = segments.collect();
+    let valid = (head.is_some() || !first.is_empty()) && calls.iter().all(|call| matches!(
+        call.first(),
+        Some(SyntaxNode::NamedNode { identifier, children, .. }) if children.is_empty() && identifier != PIPE
+    ));
+    if !valid {
+        return rebuild(head, starts_at, children);
+    }
 
-    } else {
-        if log_enabled!(Level::Trace) {
-            trace!("{:->width$}-\"{}\" at {} (child of {})", "", new_identifier, identifier_starts_at, syntax_node, width = level);
+    let mut result = rebuild(head, starts_at, first.to_vec());
+    for call in calls {
+        if let SyntaxNode::NamedNode { identifier, starts_at, .. } = &call[0] {
+            let mut parameters = vec![result];
+            parameters.extend_from_slice(&call[1..]);
+            result = SyntaxNode::NamedNode {
+                identifier: identifier.clone(),
+                starts_at: starts_at.clone(),
+                children: parameters,
+            };
         }
-        syntax_node.add_child(SyntaxNode::NamedNode {
-                identifier: new_identifier.to_string(),
-                children: vec![],
-                starts_at: Position::RelativeToCodeStart(identifier_starts_at),
-            })
     }
+    result
+}
+
+fn is_pipe(node: &SyntaxNode) -> bool {
+    matches!(node, SyntaxNode::NamedNode { identifier, children, .. } if identifier == PIPE && children.is_empty())
 }
 
 #[cfg(test)]
@@ -157,6 +258,7 @@ mod tests {
     use rubble_templates_core::ast::SyntaxNode::{AnonymousNode, NamedNode};
     use log::LevelFilter;
     use rubble_templates_core::units::Position;
+    use rubble_templates_core::ast::{ParseError, ParseErrorKind, SyntaxNode, Literal};
     use crate::parser::parse_ast;
 
     fn init() {
@@ -171,7 +273,7 @@ mod tests {
         init();
 
         let input = "{{ (list 1 2 (if a b c)) }}";
-        let actual = parse_ast(input,"{{", "}}");
+        let actual = parse_ast(input,"{{", "}}").unwrap();
 
         let expected = AnonymousNode {
             starts_at: Position::RelativeToCodeStart(0),
@@ -180,33 +282,31 @@ mod tests {
                     identifier: "list".to_string(),
                     starts_at: Position::RelativeToCodeStart(2),
                     children: vec![
-                        NamedNode {
-                            identifier: "1".to_string(),
-                            children: vec![],
+                        SyntaxNode::Literal {
+                            value: Literal::Integer(1),
                             starts_at: Position::RelativeToCodeStart(7),
                         },
-                        NamedNode {
-                            identifier: "2".to_string(),
+                        SyntaxNode::Literal {
+                            value: Literal::Integer(2),
                             starts_at: Position::RelativeToCodeStart(9),
-                            children: vec![],
                         },
                         NamedNode {
                             identifier: "if".to_string(),
-                            starts_at: Position::RelativeToCodeStart(11),
+                            starts_at: Position::RelativeToCodeStart(12),
                             children: vec![
                                 NamedNode {
                                     identifier: "a".to_string(),
-                                    starts_at: Position::RelativeToCodeStart(14),
+                                    starts_at: Position::RelativeToCodeStart(15),
                                     children: vec![],
                                 },
                                 NamedNode {
                                     identifier: "b".to_string(),
-                                    starts_at: Position::RelativeToCodeStart(16),
+                                    starts_at: Position::RelativeToCodeStart(17),
                                     children: vec![],
                                 },
                                 NamedNode {
                                     identifier: "c".to_string(),
-                                    starts_at: Position::RelativeToCodeStart(18),
+                                    starts_at: Position::RelativeToCodeStart(19),
                                     children: vec![],
                                 },
                             ],
@@ -217,4 +317,130 @@ mod tests {
         };
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn should_unescape_string_literals() {
+        let input = r#"{{ concat "a\"b\\c" "\n\t\u{17C}" "\q\u{zz}" }}"#;
+        let actual = parse_ast(input, "{{", "}}").unwrap();
+
+        let expected = named("concat", 1, vec![
+            literal(Literal::String("a\"b\\c".to_string()), 8),
+            literal(Literal::String("\n\t\u{17C}".to_string()), 18),
+            literal(Literal::String("\\q\\u{zz}".to_string()), 32),
+        ]);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_parse_literals() {
+        let actual = parse_ast("{{ f 1 -2 +3.5 1e3 true false inf NaN 1.2.3 \"x\"y }}", "{{", "}}").unwrap();
+
+        let expected = named("f", 1, vec![
+            literal(Literal::Integer(1), 3),
+            literal(Literal::Integer(-2), 5),
+            literal(Literal::Float(3.5), 8),
+            literal(Literal::Float(1000.0), 13),
+            literal(Literal::Boolean(true), 17),
+            literal(Literal::Boolean(false), 22),
+            named("inf", 28, vec![]),
+            named("NaN", 32, vec![]),
+            named("1.2.3", 36, vec![]),
+            literal(Literal::String("x".to_string()), 42),
+            named("y", 45, vec![]),
+        ]);
+        assert_eq!(actual, expected);
+
+        let actual = parse_ast("{{ \"text\" }}", "{{", "}}").unwrap();
+        assert_eq!(actual, AnonymousNode {
+            starts_at: Position::RelativeToCodeStart(0),
+            children: vec![literal(Literal::String("text".to_string()), 1)],
+        });
+    }
+
+    fn literal(value: Literal, starts_at: usize) -> SyntaxNode {
+        SyntaxNode::Literal {
+            value,
+            starts_at: Position::RelativeToCodeStart(starts_at),
+        }
+    }
+
+    fn named(identifier: &str, starts_at: usize, children: Vec<SyntaxNode>) -> SyntaxNode {
+        NamedNode {
+            identifier: identifier.to_string(),
+            starts_at: Position::RelativeToCodeStart(starts_at),
+            children,
+        }
+    }
+
+    #[test]
+    fn should_desugar_pipes() {
+        let actual = parse_ast("{{ concat a b | trim|replace \"|\" (x | y) }}", "{{", "}}").unwrap();
+
+        let expected = named("replace", 19, vec![
+            named("trim", 14, vec![
+                named("concat", 1, vec![
+                    named("a", 8, vec![]),
+                    named("b", 10, vec![]),
+                ]),
+            ]),
+            literal(Literal::String("|".to_string()), 27),
+            named("y", 36, vec![
+                named("x", 32, vec![]),
+            ]),
+        ]);
+        assert_eq!(actual, expected);
+
+        let actual = parse_ast("{{ (list a) | len }}", "{{", "}}").unwrap();
+        let expected = named("len", 12, vec![
+            AnonymousNode {
+                starts_at: Position::RelativeToCodeStart(0),
+                children: vec![named("list", 2, vec![named("a", 7, vec![])])],
+            },
+        ]);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_report_malformed_code() {
+        let cases = [
+            ("{{ plus (times 1 2 }}", 6, ParseErrorKind::UnclosedParenthesis),
+            ("{{ plus (times (1) 2 }}", 6, ParseErrorKind::UnclosedParenthesis),
+            ("{{ plus 1 2) }}", 9, ParseErrorKind::UnexpectedParenthesis),
+            ("{{ concat \"a) b }}", 8, ParseErrorKind::UnterminatedString),
+            ("{{ concat \"a\\\" }}", 8, ParseErrorKind::UnterminatedString),
+            ("{{ plus () 1 }}", 6, ParseErrorKind::EmptyCall),
+            ("{{ ( ) }}", 1, ParseErrorKind::EmptyCall),
+            ("{{ | a }}", 1, ParseErrorKind::MisplacedPipe),
+            ("{{ a | }}", 3, ParseErrorKind::MisplacedPipe),
+            ("{{ a | | b }}", 3, ParseErrorKind::MisplacedPipe),
+            ("{{ a | (b c) }}", 3, ParseErrorKind::MisplacedPipe),
+            ("{{ plus 9223372036854775808 1 }}", 6, ParseErrorKind::IntegerOutOfRange),
+            ("{{ plus (-9223372036854775809) }}", 7, ParseErrorKind::IntegerOutOfRange),
+            ("{{ (a | ) }}", 4, ParseErrorKind::MisplacedPipe),
+        ];
+
+        for (input, position, kind) in cases.iter().cloned() {
+            assert_eq!(parse_ast(input, "{{", "}}"), Err(ParseError::new(Position::RelativeToCodeStart(position), kind)), "{}", input);
+        }
+
+        for input in &["{{ }}", "{{ concat \")(|\\\"\" }}", "{{ (a) | b }}", "{{ ((a b)) }}", "{{ plus -9223372036854775808 1e19 9223372036854775808.0 }}"] {
+            assert!(parse_ast(input, "{{", "}}").is_ok(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn should_parse_code_without_spaces() {
+        assert_eq!(parse_ast("{{x}}", "{{", "}}"), Ok(named("x", 0, vec![])));
+        assert_eq!(parse_ast("{{f(g x)}}", "{{", "}}"), Ok(named("f", 0, vec![named("g", 2, vec![named("x", 4, vec![])])])));
+    }
+
+    #[test]
+    fn should_use_byte_positions_with_any_whitespace() {
+        let actual = parse_ast("{{ żółw\t(zażółć\n1) }}", "{{", "}}").unwrap();
+
+        let expected = named("żółw", 1, vec![
+            named("zażółć", 10, vec![literal(Literal::Integer(1), 21)]),
+        ]);
+        assert_eq!(actual, expected);
+    }
 }
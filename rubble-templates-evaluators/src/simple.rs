@@ -2,4 +2,7 @@
 
 pub mod evaluator;
 pub mod compiler;
-pub mod template;
\ No newline at end of file
+pub mod template;
+pub mod path;
+pub mod document;
+pub mod escape;
\ No newline at end of file
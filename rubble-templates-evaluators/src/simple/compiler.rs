@@ -1,20 +1,215 @@
 //! Compiler for [`Template`](crate::simple::template::Template), evaluates code blocks and joins everything
 
-use rubble_templates_core::evaluator::{Context, Evaluator};
+use std::collections::{BTreeMap, HashMap};
+use rubble_templates_core::escaper::Escaper;
+use rubble_templates_core::evaluator::{Context, Evaluator, SyntaxError, EvaluationError};
 use rubble_templates_core::template::{EvaluableMixedContent, TemplateSlice};
 use rubble_templates_core::compiler::{CompilationError, Compiler};
-use rubble_templates_core::units::Position;
-use crate::simple::template::{Template, EvaluableMixedContentIterator, START_PATTERN, END_PATTERN};
-use crate::parser::parse_ast;
+use rubble_templates_core::value::Value;
+use crate::simple::template::{Template, EvaluableMixedContentIterator, Syntax};
+use crate::simple::document::{Document, DocumentNode, CodeBlock, ParseOptions};
+use crate::simple::escape::{Escaping, escape_value};
 
+/// Compiles a [Template] by evaluating its code blocks and joining them with the text.
+///
+/// The template is parsed into a [Document] first, which is then walked by the compiler.
+/// A [Document] can also be compiled directly with [TemplateCompiler::compile_document].
+///
+/// Supports conditional blocks that control whether the enclosed text and code is emitted:
+/// ```text
+/// {{ if condition }} ... {{ else if other_condition }} ... {{ else }} ... {{ end }}
+/// ```
+/// Conditions are evaluated by the engine, their results are checked with [Value::is_truthy].
+///
+/// Also supports loop blocks over lists and maps, with an optional `else` branch for empty collections:
+/// ```text
+/// {{ for item in items }} ... {{ else }} ... {{ end }}
+/// {{ for key, value in map }} ... {{ end }}
+/// ```
+/// Loop variables are set in a nested scope of the [Context]. In the loop body, a `loop` variable
+/// contains the metadata of the current iteration: `loop.index` (starting from 0), `loop.first`, `loop.last` and `loop.length`.
+///
+/// Code delimiters can be changed with [TemplateCompiler::with_syntax], they are used for templates that do not have their own [Syntax]:
+/// ```
+/// use std::collections::HashMap;
+/// use rubble_templates_core::compiler::Compiler;
+/// use rubble_templates_core::evaluator::Context;
+/// use rubble_templates_evaluators::simple::template::{Template, Syntax};
+/// use rubble_templates_evaluators::simple::evaluator::SimpleEvaluationEngine;
+/// use rubble_templates_evaluators::simple::compiler::TemplateCompiler;
+///
+/// let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(HashMap::new()))
+///     .with_syntax(Syntax::new("[[", "]]"));
+/// let template = Template::from("{{ .Values.name }}: [[ name ]]".to_string());
+/// let mut context = Context::empty();
+/// context.set_variable("name", "rubble");
+///
+/// assert_eq!(compiler.compile(&template, context).unwrap(), "{{ .Values.name }}: rubble");
+/// ```
+///
+/// Other parsing options, eg. removing lines that contain only block tags, can be set with [TemplateCompiler::with_options].
+///
+/// Results of code blocks can be escaped (eg. for HTML output) with [TemplateCompiler::with_escaping]
+/// or a custom [Escaper] set with [TemplateCompiler::with_escaper].
+/// The escaper can be changed for a region of the template, using a name of one of the [Escaping] policies
+/// (eg. `json`) or an escaper registered with [TemplateCompiler::with_named_escaper]:
+/// ```text
+/// {{ escape "json" }} ... {{ end }}
+/// ```
+///
+/// By default, the compilation stops at the first block that fails to evaluate.
+/// To find all errors in a template at once, use [TemplateCompiler::with_error_mode].
 pub struct TemplateCompiler<E: Evaluator> {
-    engine: E
+    engine: E,
+    options: ParseOptions,
+    escaper: Box<dyn Escaper>,
+    escapers: HashMap<String, Box<dyn Escaper>>,
+    error_mode: ErrorMode,
+}
+
+/// Describes how a [TemplateCompiler] handles blocks that fail to evaluate.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum ErrorMode {
+    /// Stops at the first failing block and returns its error.
+    #[default]
+    FailFast,
+    /// Continues past failing blocks and returns all errors in [CompilationError::Multiple].
+    ///
+    /// Malformed blocks (eg. with unbalanced parenthesis or unclosed `if` blocks) are collected too,
+    /// the rest of template is still compiled.
+    ///
+    /// When the placeholder is set, the partial output is returned in the error too,
+    /// with the placeholder in place of every failed block.
+    Collect {
+        placeholder: Option<String>,
+    },
 }
 
 impl<E> TemplateCompiler<E> where E: Evaluator {
     pub fn new(engine: E) -> TemplateCompiler<E> {
+        let escapers = Escaping::NAMED.iter()
+            .map(|(name, escaping)| (name.to_string(), Box::new(*escaping) as Box<dyn Escaper>))
+            .collect();
+
+        TemplateCompiler {
+            engine,
+            options: ParseOptions::default(),
+            escaper: Box::new(Escaping::None),
+            escapers,
+            error_mode: ErrorMode::default(),
+        }
+    }
+
+    /// Sets the [Escaping] policy, that is applied to results of all code blocks except for [Value::Safe] values.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use rubble_templates_core::compiler::Compiler;
+    /// use rubble_templates_core::evaluator::Context;
+    /// use rubble_templates_core::value::Value;
+    /// use rubble_templates_evaluators::simple::template::Template;
+    /// use rubble_templates_evaluators::simple::evaluator::SimpleEvaluationEngine;
+    /// use rubble_templates_evaluators::simple::compiler::TemplateCompiler;
+    /// use rubble_templates_evaluators::simple::escape::Escaping;
+    ///
+    /// let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(HashMap::new()))
+    ///     .with_escaping(Escaping::Html);
+    /// let template = Template::from("<p>{{ comment }}</p>{{ footer }}".to_string());
+    /// let mut context = Context::empty();
+    /// context.set_variable("comment", "<script>alert(1)</script>");
+    /// context.set_variable("footer", Value::Safe("<hr>".to_string()));
+    ///
+    /// assert_eq!(
+    ///     compiler.compile(&template, context).unwrap(),
+    ///     "<p>&lt;script&gt;alert(1)&lt;/script&gt;</p><hr>"
+    /// );
+    /// ```
+    pub fn with_escaping(self, escaping: Escaping) -> TemplateCompiler<E> {
+        self.with_escaper(escaping)
+    }
+
+    /// Sets the [Escaper] that is applied to results of all code blocks except for [Value::Safe] values.
+    pub fn with_escaper<T>(self, escaper: T) -> TemplateCompiler<E> where T: Escaper + 'static {
+        TemplateCompiler {
+            escaper: Box::new(escaper),
+            ..self
+        }
+    }
+
+    /// Registers an [Escaper] that can be used in `{{ escape "name" }}` blocks.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use rubble_templates_core::compiler::Compiler;
+    /// use rubble_templates_core::evaluator::Context;
+    /// use rubble_templates_evaluators::simple::template::Template;
+    /// use rubble_templates_evaluators::simple::evaluator::SimpleEvaluationEngine;
+    /// use rubble_templates_evaluators::simple::compiler::TemplateCompiler;
+    ///
+    /// let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(HashMap::new()))
+    ///     .with_named_escaper("upper", |text: &str| text.to_uppercase());
+    /// let template = Template::from("{{ name }} {{ escape upper }}{{ name }}{{ end }}".to_string());
+    /// let mut context = Context::empty();
+    /// context.set_variable("name", "Joe");
+    ///
+    /// assert_eq!(compiler.compile(&template, context).unwrap(), "Joe JOE");
+    /// ```
+    pub fn with_named_escaper<T>(self, name: &str, escaper: T) -> TemplateCompiler<E> where T: Escaper + 'static {
+        let mut escapers = self.escapers;
+        escapers.insert(name.to_string(), Box::new(escaper));
+        TemplateCompiler {
+            escapers,
+            ..self
+        }
+    }
+
+    /// Sets the code delimiters used for templates without their own [Syntax].
+    pub fn with_syntax(self, syntax: Syntax) -> TemplateCompiler<E> {
+        TemplateCompiler {
+            options: ParseOptions {
+                syntax: Some(syntax),
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Sets the [ParseOptions] used to parse templates.
+    pub fn with_options(self, options: ParseOptions) -> TemplateCompiler<E> {
         TemplateCompiler {
-            engine
+            options,
+            ..self
+        }
+    }
+
+    /// Sets the [ErrorMode], which decides whether the compilation stops at the first failing block.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use rubble_templates_core::compiler::{Compiler, CompilationError};
+    /// use rubble_templates_core::evaluator::Context;
+    /// use rubble_templates_evaluators::simple::template::Template;
+    /// use rubble_templates_evaluators::simple::evaluator::SimpleEvaluationEngine;
+    /// use rubble_templates_evaluators::simple::compiler::{TemplateCompiler, ErrorMode};
+    ///
+    /// let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(HashMap::new()))
+    ///     .with_error_mode(ErrorMode::Collect { placeholder: Some("???".to_string()) });
+    /// let template = Template::from("{{ first }}, {{ name }}, {{ last }}".to_string());
+    /// let mut context = Context::empty();
+    /// context.set_variable("name", "Joe");
+    ///
+    /// match compiler.compile(&template, context) {
+    ///     Err(CompilationError::Multiple { errors, output }) => {
+    ///         assert_eq!(errors.len(), 2);
+    ///         assert_eq!(output, Some("???, Joe, ???".to_string()));
+    ///     }
+    ///     other => panic!("Unexpected result: {:?}", other),
+    /// }
+    /// ```
+    pub fn with_error_mode(self, error_mode: ErrorMode) -> TemplateCompiler<E> {
+        TemplateCompiler {
+            error_mode,
+            ..self
         }
     }
 }
@@ -25,39 +220,254 @@ impl<'a, E> Compiler<&'a Template> for TemplateCompiler<E> where E: Evaluator {
 
     fn compile<C>(&self, content: C, context: Context) -> Result<String, CompilationError>
         where C: EvaluableMixedContent<Item=Self::Item, IntoIter=Self::ItemIterator> {
-        let mut result = String::new();
+        // the template is scanned again by the document parser to apply the options of this compiler
+        let slices = content.into_iter();
+        match &self.error_mode {
+            ErrorMode::FailFast => {
+                let document = Document::parse_with(slices.source, &self.options)?;
+                self.compile_document(&document, context)
+            }
+            ErrorMode::Collect { .. } => {
+                // malformed blocks are reported together with the blocks that fail to evaluate
+                let (document, errors) = Document::parse_recovering(slices.source, &self.options);
+                self.compile_collecting(&document, context, errors)
+            }
+        }
+    }
+}
+
+impl<E> TemplateCompiler<E> where E: Evaluator {
+    /// Parses a [Template] once, so it can be rendered many times with [TemplateCompiler::render].
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use rubble_templates_core::evaluator::Context;
+    /// use rubble_templates_evaluators::simple::template::Template;
+    /// use rubble_templates_evaluators::simple::evaluator::SimpleEvaluationEngine;
+    /// use rubble_templates_evaluators::simple::compiler::TemplateCompiler;
+    ///
+    /// let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(HashMap::new()));
+    /// let compiled = compiler.precompile(&Template::from("Hello {{ name }}!".to_string())).unwrap();
+    ///
+    /// for name in &["Joe", "Ann"] {
+    ///     let mut context = Context::empty();
+    ///     context.set_variable("name", *name);
+    ///     assert_eq!(compiler.render(&compiled, context).unwrap(), format!("Hello {}!", name));
+    /// }
+    /// ```
+    pub fn precompile(&self, template: &Template) -> Result<CompiledTemplate, CompilationError> {
+        Document::parse_with(template, &self.options).map(CompiledTemplate::from)
+    }
+
+    /// Renders a [CompiledTemplate] using the given [Context].
+    pub fn render(&self, template: &CompiledTemplate, context: Context) -> Result<String, CompilationError> {
+        self.compile_document(&template.document, context)
+    }
+
+    /// Compiles an already parsed [Document].
+    pub fn compile_document(&self, document: &Document, context: Context) -> Result<String, CompilationError> {
+        self.compile_collecting(document, context, vec![])
+    }
+
+    /// Compiles a [Document], the errors found so far (eg. while parsing) are returned together with the evaluation errors.
+    fn compile_collecting(&self, document: &Document, context: Context, errors: Vec<CompilationError>) -> Result<String, CompilationError> {
+        let mut output = Output {
+            text: String::new(),
+            escaper: self.escaper.as_ref(),
+            errors,
+        };
 
         let mut context = context;
-        let context = &mut context;
-
-        for item in content {
-            let compiled = match item {
-                TemplateSlice::Text { value, .. } => value.to_string(),
-                TemplateSlice::Code { value, start_position, .. } => self.engine
-                    .evaluate(&parse_ast(value, START_PATTERN, END_PATTERN), context)
-                    .map_err(|err| CompilationError::EvaluationFailed {
-                        error: err,
-                        position: Position::Absolute(start_position),
-                        source: value.to_string(),
-                    })?,
-            };
-
-            result.push_str(compiled.as_str());
+        self.compile_nodes(&document.nodes, &mut context, &mut output)?;
+
+        let Output { text, mut errors, .. } = output;
+        errors.sort_by_key(|error| error.offset().unwrap_or(usize::MAX));
+        match &self.error_mode {
+            ErrorMode::Collect { placeholder } if !errors.is_empty() => Err(CompilationError::Multiple {
+                errors,
+                output: placeholder.as_ref().map(|_| text),
+            }),
+            _ => Ok(text),
+        }
+    }
+
+    fn compile_nodes<'e>(&'e self, nodes: &[DocumentNode], context: &mut Context, output: &mut Output<'e>) -> Result<(), CompilationError> {
+        for node in nodes {
+            match node {
+                DocumentNode::Text { value, .. } => output.text.push_str(value),
+                DocumentNode::Comment { .. } => {}
+                DocumentNode::Invalid { error, .. } => self.recover(error.clone(), output)?,
+                DocumentNode::Expression(code) => match self.evaluate(code, context) {
+                    Ok(value) => output.text.push_str(&escape_value(output.escaper, &value)),
+                    Err(error) => self.recover(error, output)?,
+                },
+                DocumentNode::Conditional { branches, otherwise, .. } => {
+                    let mut selected = Some(otherwise);
+                    for branch in branches {
+                        match self.evaluate(&branch.condition, context) {
+                            Ok(condition) if condition.is_truthy() => selected = Some(&branch.body),
+                            Ok(_) => continue,
+                            Err(error) => {
+                                // none of the branches is emitted, as it is unknown which one should be
+                                self.recover(error, output)?;
+                                selected = None;
+                            }
+                        }
+                        break;
+                    }
+
+                    if let Some(selected) = selected {
+                        self.compile_nodes(selected, context, output)?;
+                    }
+                }
+                DocumentNode::Loop { variables, collection, body, otherwise, .. } =>
+                    self.compile_loop(variables, collection, body, otherwise, context, output)?,
+                DocumentNode::Escape { escaper, tag, body, .. } => {
+                    // the body of a block with an unknown escaper is still compiled (with the current escaper) to find other errors
+                    let escaper = match self.escapers.get(escaper) {
+                        Some(escaper) => escaper.as_ref(),
+                        None => {
+                            self.recover(tag.evaluation_failed(SyntaxError::new(EvaluationError::UnknownSymbol {
+                                symbol: escaper.clone(),
+                            })), output)?;
+                            output.escaper
+                        }
+                    };
+
+                    let previous = std::mem::replace(&mut output.escaper, escaper);
+                    let compiled = self.compile_nodes(body, context, output);
+                    output.escaper = previous;
+                    compiled?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn compile_loop<'e>(&'e self, variables: &[String], collection: &CodeBlock, body: &[DocumentNode], otherwise: &[DocumentNode], context: &mut Context, output: &mut Output<'e>) -> Result<(), CompilationError> {
+        let collection_value = match self.evaluate(collection, context) {
+            Ok(value) => value,
+            Err(error) => return self.recover(error, output),
+        };
+        let entries: Vec<(Value, Value)> = match collection_value {
+            Value::List(items) => items.into_iter()
+                .enumerate()
+                .map(|(index, item)| (Value::Integer(index as i64), item))
+                .collect(),
+            Value::Map(entries) => entries.into_iter()
+                .map(|(key, value)| (Value::String(key), value))
+                .collect(),
+            Value::Null => vec![],
+            other => return self.recover(collection.evaluation_failed(SyntaxError::at_position(collection.ast.starts_at().clone(), EvaluationError::InvalidValues {
+                description: Some(format!("Cannot iterate over {}", other.type_name())),
+                values: vec![other],
+            })), output),
+        };
+
+        if entries.is_empty() {
+            return self.compile_nodes(otherwise, context, output);
+        }
+
+        let length = entries.len();
+        for (index, (key, value)) in entries.into_iter().enumerate() {
+            context.push_scope();
+            match variables {
+                [key_name, value_name] => {
+                    context.set_variable(key_name, key);
+                    context.set_variable(value_name, value);
+                }
+                _ => context.set_variable(&variables[0], value),
+            }
+            context.set_variable("loop", loop_metadata(index, length));
+
+            let compiled = self.compile_nodes(body, context, output);
+            context.pop_scope();
+            compiled?;
+        }
+
+        Ok(())
+    }
+
+    /// Handles an error of a failed block according to the [ErrorMode].
+    ///
+    /// The error is returned back when the compilation should stop, otherwise it is collected in the output.
+    fn recover(&self, error: CompilationError, output: &mut Output) -> Result<(), CompilationError> {
+        match &self.error_mode {
+            ErrorMode::FailFast => Err(error),
+            ErrorMode::Collect { placeholder } => {
+                if let Some(placeholder) = placeholder {
+                    output.text.push_str(placeholder);
+                }
+                // blocks in loops can fail with the same error in every iteration
+                if !output.errors.contains(&error) {
+                    output.errors.push(error);
+                }
+                Ok(())
+            }
         }
+    }
+
+    fn evaluate(&self, code: &CodeBlock, context: &mut Context) -> Result<Value, CompilationError> {
+        self.engine
+            .evaluate(&code.ast, context)
+            .map_err(|err| code.evaluation_failed(err))
+    }
+}
+
+/// Compiled text with the escaper that is currently applied to code results and errors collected so far.
+struct Output<'e> {
+    text: String,
+    escaper: &'e dyn Escaper,
+    errors: Vec<CompilationError>,
+}
+
+/// A [Template] that was already parsed by [TemplateCompiler::precompile].
+///
+/// It does not borrow the source template and can be rendered repeatedly with different contexts,
+/// without scanning the template and parsing its code again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompiledTemplate {
+    document: Document,
+}
+
+impl CompiledTemplate {
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+}
 
-        Ok(result)
+impl From<Document> for CompiledTemplate {
+    fn from(document: Document) -> Self {
+        CompiledTemplate {
+            document
+        }
     }
 }
 
+/// Creates the `loop` variable that is available in loop blocks.
+fn loop_metadata(index: usize, length: usize) -> Value {
+    let mut metadata = BTreeMap::new();
+    metadata.insert("index".to_string(), Value::Integer(index as i64));
+    metadata.insert("first".to_string(), Value::Bool(index == 0));
+    metadata.insert("last".to_string(), Value::Bool(index + 1 == length));
+    metadata.insert("length".to_string(), Value::Integer(length as i64));
+    Value::Map(metadata)
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
     use rubble_templates_core::evaluator::{Context, SyntaxError, EvaluationError};
     use rubble_templates_core::units::Position;
-    use crate::simple::template::Template;
+    use rubble_templates_core::value::Value;
+    use crate::simple::template::{Template, Syntax};
     use crate::simple::evaluator::SimpleEvaluationEngine;
-    use crate::simple::compiler::TemplateCompiler;
+    use crate::simple::compiler::{TemplateCompiler, ErrorMode};
+    use crate::simple::document::ParseOptions;
+    use crate::simple::escape::Escaping;
     use rubble_templates_core::compiler::{Compiler, CompilationError};
+    use rubble_templates_core::ast::{ParseError, ParseErrorKind};
 
     #[test]
     fn should_compile_template() {
@@ -72,6 +482,255 @@ mod tests {
         assert_eq!(result, Ok("Some simple-template. Hello world - or something".to_string()));
     }
 
+    #[test]
+    fn should_compile_typed_variables() {
+        let template = Template::from("{{ count }} items: {{ items }}, enabled: {{ enabled }}{{ missing }}".to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine);
+        let mut context = Context::empty();
+        context.set_variable("count", 3);
+        context.set_variable("items", vec!["a", "b", "c"]);
+        context.set_variable("enabled", true);
+        context.set_variable("missing", Value::Null);
+
+        let result = compiler.compile(&template, context);
+
+        assert_eq!(result, Ok("3 items: [a, b, c], enabled: true".to_string()));
+    }
+
+    #[test]
+    fn should_compile_nested_variables() {
+        let template = Template::from("{{ user.name }} lives in {{ user[\"home address\"].city }}".to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine);
+        let mut address = HashMap::new();
+        address.insert("city".to_string(), "Warsaw");
+        let mut user = HashMap::new();
+        user.insert("name".to_string(), Value::from("Joe"));
+        user.insert("home address".to_string(), Value::from(address));
+        let mut context = Context::empty();
+        context.set_variable("user", user);
+
+        let result = compiler.compile(&template, context);
+
+        assert_eq!(result, Ok("Joe lives in Warsaw".to_string()));
+    }
+
+    #[test]
+    fn should_compile_nested_variables_with_escaped_keys() {
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine);
+        let mut map = HashMap::new();
+        map.insert("a\"b".to_string(), "quoted");
+
+        let mut context = Context::empty();
+        context.set_variable("map", map.clone());
+        let template = Template::from(r#"{{ map["a\"b"] }}"#.to_string());
+        assert_eq!(compiler.compile(&template, context), Ok("quoted".to_string()));
+
+        let mut context = Context::empty();
+        context.set_variable("map", map);
+        let template = Template::from(r#"{{ map["a\"b"].c }}"#.to_string());
+        assert_eq!(compiler.compile(&template, context), Err(CompilationError::EvaluationFailed {
+            error: Box::new(SyntaxError::at_position(Position::Absolute(15), EvaluationError::UnknownPathSegment {
+                path: r#"map["a\"b"].c"#.to_string(),
+                segment: "c".to_string(),
+            })),
+            position: Position::Absolute(0),
+            source: r#"{{ map["a\"b"].c }}"#.to_string(),
+        }));
+    }
+
+    #[test]
+    fn should_compile_conditional_blocks() {
+        let template = Template::from("{{ if admin }}Admin panel{{ else if user }}Hello {{ user }}{{ else }}Log in{{ end }}!".to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine);
+
+        let mut context = Context::empty();
+        context.set_variable("admin", true);
+        assert_eq!(compiler.compile(&template, context), Ok("Admin panel!".to_string()));
+
+        let mut context = Context::empty();
+        context.set_variable("admin", false);
+        context.set_variable("user", "Joe");
+        assert_eq!(compiler.compile(&template, context), Ok("Hello Joe!".to_string()));
+
+        let mut context = Context::empty();
+        context.set_variable("admin", 0);
+        context.set_variable("user", "");
+        assert_eq!(compiler.compile(&template, context), Ok("Log in!".to_string()));
+    }
+
+    #[test]
+    fn should_compile_loop_blocks() {
+        let template = Template::from("{{ for item in items }}{{ loop.index }}:{{ item.name }}{{ if loop.last }}.{{ else }}, {{ end }}{{ else }}none{{ end }}".to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine);
+
+        let mut first = HashMap::new();
+        first.insert("name".to_string(), "a");
+        let mut second = HashMap::new();
+        second.insert("name".to_string(), "b");
+        let mut context = Context::empty();
+        context.set_variable("items", vec![first, second]);
+        context.set_variable("item", "outer");
+        assert_eq!(compiler.compile(&template, context), Ok("0:a, 1:b.".to_string()));
+
+        let mut context = Context::empty();
+        context.set_variable("items", Vec::<String>::new());
+        assert_eq!(compiler.compile(&template, context), Ok("none".to_string()));
+    }
+
+    #[test]
+    fn should_compile_map_loop_blocks() {
+        let template = Template::from("{{ for key, value in map }}{{ key }}={{ value }};{{ end }}{{ for value in map }}{{ value }}{{ end }}{{ key }}".to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine);
+
+        let mut map = HashMap::new();
+        map.insert("b".to_string(), 2);
+        map.insert("a".to_string(), 1);
+        let mut context = Context::empty();
+        context.set_variable("map", map);
+        context.set_variable("key", "!");
+        assert_eq!(compiler.compile(&template, context), Ok("a=1;b=2;12!".to_string()));
+    }
+
+    #[test]
+    fn should_not_evaluate_skipped_branches() {
+        let template = Template::from("{{ if flag }}{{ unknown }}{{ else }}skipped{{ end }}".to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine);
+        let mut context = Context::empty();
+        context.set_variable("flag", Value::Null);
+
+        assert_eq!(compiler.compile(&template, context), Ok("skipped".to_string()));
+    }
+
+    #[test]
+    fn should_compile_with_custom_syntax() {
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine).with_syntax(Syntax::new("<%=", "%>"));
+
+        let template = Template::from("{{ x }} <%= if x %><%= x %>{{ end }}<%= end %>".to_string());
+        let mut context = Context::empty();
+        context.set_variable("x", 1);
+        assert_eq!(compiler.compile(&template, context), Ok("{{ x }} 1{{ end }}".to_string()));
+
+        let template = Template::from("<%= x %> \\(( x ))".to_string())
+            .with_syntax(Syntax::new("\\((", "))"));
+        let mut context = Context::empty();
+        context.set_variable("x", 1);
+        assert_eq!(compiler.compile(&template, context), Ok("<%= x %> 1".to_string()));
+    }
+
+    #[test]
+    fn should_compile_escaped_delimiters_and_strings() {
+        let template = Template::from(r#"\{{ name }} = {{ "}} \"{{\"\t\u{1F600}" }}{{ "" }}"#.to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine);
+
+        let result = compiler.compile(&template, Context::empty());
+
+        assert_eq!(result, Ok("{{ name }} = }} \"{{\"\t\u{1F600}".to_string()));
+    }
+
+    #[test]
+    fn should_compile_with_whitespace_control() {
+        let template = Template::from("items:\n  {{ for item in items }}\n  - {{ item }}\n  {{ end }}\ncount: {{- count -}}  !".to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine).with_options(ParseOptions {
+            trim_block_lines: true,
+            ..ParseOptions::default()
+        });
+        let mut context = Context::empty();
+        context.set_variable("items", vec!["a", "b"]);
+        context.set_variable("count", 2);
+
+        let result = compiler.compile(&template, context);
+
+        assert_eq!(result, Ok("items:\n  - a\n  - b\ncount:2!".to_string()));
+    }
+
+    #[test]
+    fn should_skip_comments() {
+        let template = Template::from("a{{# {{ unknown }} #}}b{{# \n #}}".to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine);
+
+        assert_eq!(compiler.compile(&template, Context::empty()), Ok("ab".to_string()));
+    }
+
+    #[test]
+    fn should_escape_html_output() {
+        let template = Template::from("<a title=\"{{ title }}\" href='{{ url }}'>{{ text }}</a>{{ html }}".to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine).with_escaping(Escaping::Html);
+        let mut context = Context::empty();
+        context.set_variable("title", "\" onmouseover=\"alert(1)");
+        context.set_variable("url", "x' onclick='alert(1)");
+        context.set_variable("text", "<b>Tom & Jerry</b>");
+        context.set_variable("html", Value::Safe("<br>".to_string()));
+
+        let result = compiler.compile(&template, context);
+
+        assert_eq!(result, Ok("<a title=\"&quot; onmouseover=&quot;alert(1)\" href='x&#39; onclick=&#39;alert(1)'>&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;</a><br>".to_string()));
+    }
+
+    #[test]
+    fn should_escape_xml_output() {
+        let template = Template::from("<item name='{{ name }}'>{{ value }}</item>".to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine).with_escaping(Escaping::Xml);
+        let mut context = Context::empty();
+        context.set_variable("name", "O'Neil");
+        context.set_variable("value", vec!["<a>", "&"]);
+
+        let result = compiler.compile(&template, context);
+
+        assert_eq!(result, Ok("<item name='O&apos;Neil'>[&lt;a&gt;, &amp;]</item>".to_string()));
+    }
+
+    #[test]
+    fn should_switch_escaper_in_blocks() {
+        let template = Template::from("<script>var user = {{ escape \"json\" }}{ \"name\": {{ name }} }{{ end }};</script>\n<a href=\"/users/{{ escape url }}{{ name }}{{ end }}\">{{ name }}</a>\n{{ escape shell }}echo {{ name }}{{ escape yaml }} {{ name }}{{ end }}{{ end }}".to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine).with_escaping(Escaping::Html);
+        let mut context = Context::empty();
+        context.set_variable("name", "Jo \"Joe\" & <Doe>");
+
+        let result = compiler.compile(&template, context);
+
+        assert_eq!(result, Ok("<script>var user = { \"name\": \"Jo \\\"Joe\\\" & <Doe>\" };</script>\n<a href=\"/users/Jo%20%22Joe%22%20%26%20%3CDoe%3E\">Jo &quot;Joe&quot; &amp; &lt;Doe&gt;</a>\necho 'Jo \"Joe\" & <Doe>' \"Jo \\\"Joe\\\" & <Doe>\"".to_string()));
+
+        let template = Template::from("{{ escape unknown }}{{ end }}".to_string());
+        assert_eq!(compiler.compile(&template, Context::empty()), Err(CompilationError::EvaluationFailed {
+            error: Box::new(SyntaxError::new(EvaluationError::UnknownSymbol { symbol: "unknown".to_string() })),
+            position: Position::Absolute(0),
+            source: "{{ escape unknown }}".to_string(),
+        }));
+    }
+
+    #[test]
+    fn should_render_precompiled_template_many_times() {
+        let template = Template::from("{{ for item in items }}{{ item }};{{ end }}{{ title }}".to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine);
+        let compiled = compiler.precompile(&template).unwrap();
+        drop(template);
+
+        let mut context = Context::empty();
+        context.set_variable("items", vec![1, 2]);
+        context.set_variable("title", "first");
+        assert_eq!(compiler.render(&compiled, context), Ok("1;2;first".to_string()));
+
+        let mut context = Context::empty();
+        context.set_variable("items", vec!["a"]);
+        context.set_variable("title", "second");
+        assert_eq!(compiler.render(&compiled, context), Ok("a;second".to_string()));
+    }
+
     #[test]
     fn should_return_error_during_evaluation() {
         let template = Template::from("Should fail. {{ variable }}".to_string());
@@ -81,12 +740,146 @@ mod tests {
         let result = compiler.compile(&template, Context::empty());
 
         assert_eq!(result, Err(CompilationError::EvaluationFailed {
-            error: SyntaxError::at_position(Position::RelativeToCodeStart(1), EvaluationError::UnknownSymbol {
+            error: Box::new(SyntaxError::at_position(Position::Absolute(16), EvaluationError::UnknownSymbol {
                 symbol: "variable".to_string()
             }
-            ),
+            )),
             position: Position::Absolute(13),
             source: "{{ variable }}".to_string()
         }));
     }
+
+    #[test]
+    fn should_report_unterminated_string_in_code() {
+        let template = Template::from("Hi {{ concat \"a }} rest \"b\" {{ x }}".to_string());
+        let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(HashMap::new()));
+
+        let result = compiler.compile(&template, Context::empty());
+
+        assert_eq!(result, Err(CompilationError::ParseFailed {
+            error: ParseError::new(Position::Absolute(13), ParseErrorKind::UnterminatedString),
+            position: Position::Absolute(3),
+            source: "{{ concat \"a }}".to_string(),
+        }));
+    }
+
+    #[test]
+    fn should_collect_all_errors() {
+        let template = Template::from("{{ a }}|{{ if b }}x{{ end }}|{{ for item in items }}{{ c }}{{ end }}|{{ escape foo }}{{ d }}{{ end }}|{{ name }}".to_string());
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let mut context = Context::empty();
+        context.set_variable("items", vec![1, 2]);
+        context.set_variable("name", "Joe");
+
+        let compiler = TemplateCompiler::new(engine)
+            .with_error_mode(ErrorMode::Collect { placeholder: Some("?".to_string()) });
+        let result = compiler.compile(&template, context);
+
+        let (errors, output) = match result {
+            Err(CompilationError::Multiple { errors, output }) => (errors, output),
+            other => panic!("Expected multiple errors, got {:?}", other),
+        };
+        let errors: Vec<(String, Position)> = errors.into_iter()
+            .map(|error| match error {
+                CompilationError::EvaluationFailed { error, position, .. } => (error.to_string(), position),
+                other => panic!("Unexpected error {:?}", other),
+            })
+            .collect();
+        assert_eq!(errors, vec![
+            ("unknown symbol `a`".to_string(), Position::Absolute(0)),
+            ("unknown symbol `b`".to_string(), Position::Absolute(8)),
+            ("unknown symbol `c`".to_string(), Position::Absolute(52)),
+            ("unknown symbol `foo`".to_string(), Position::Absolute(69)),
+            ("unknown symbol `d`".to_string(), Position::Absolute(85)),
+        ]);
+        assert_eq!(output, Some("?|?|??|??|Joe".to_string()));
+
+        let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(HashMap::new()))
+            .with_error_mode(ErrorMode::Collect { placeholder: None });
+        let result = compiler.compile(&template, Context::empty());
+
+        assert!(matches!(result, Err(CompilationError::Multiple { errors, output: None }) if errors.len() == 6));
+    }
+
+    #[test]
+    fn should_collect_parse_errors() {
+        let template = Template::from("{{ (a }}|{{ for x y in items }}{{ x }}{{ end }}|{{ name }}|{{ b }}|{{ if c".to_string());
+        let mut context = Context::empty();
+        context.set_variable("name", "Joe");
+
+        let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(HashMap::new()))
+            .with_error_mode(ErrorMode::Collect { placeholder: Some("?".to_string()) });
+        let result = compiler.compile(&template, context);
+
+        let (errors, output) = match result {
+            Err(CompilationError::Multiple { errors, output }) => (errors, output),
+            other => panic!("Expected multiple errors, got {:?}", other),
+        };
+        let offsets: Vec<Option<usize>> = errors.iter().map(CompilationError::offset).collect();
+        assert!(matches!(errors[0], CompilationError::ParseFailed { .. }));
+        assert!(matches!(errors[1], CompilationError::EvaluationFailed { .. }));
+        assert!(matches!(errors[2], CompilationError::EvaluationFailed { .. }));
+        assert!(matches!(errors[3], CompilationError::UnterminatedBlock { .. }));
+        assert_eq!(offsets, vec![Some(3), Some(9), Some(62), Some(67)]);
+        assert_eq!(output, Some("?|?|Joe|?|?".to_string()));
+    }
+
+    #[test]
+    fn should_collect_errors_of_unbalanced_blocks() {
+        let template = Template::from("{{ end }}{{ if a }}x{{ else }}y{{ else }}z{{ end }}{{ for i in items }}{{ i }}".to_string());
+        let mut context = Context::empty();
+        context.set_variable("a", false);
+        context.set_variable("items", vec![1]);
+
+        let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(HashMap::new()))
+            .with_error_mode(ErrorMode::Collect { placeholder: Some("?".to_string()) });
+        let result = compiler.compile(&template, context);
+
+        match result {
+            Err(CompilationError::Multiple { errors, output }) => {
+                assert!(matches!(&errors[0], CompilationError::UnexpectedBlockTag { tag, opened_at: None, .. } if tag == "end"));
+                assert!(matches!(&errors[1], CompilationError::UnexpectedBlockTag { tag, opened_at: Some(_), .. } if tag == "else"));
+                assert!(matches!(&errors[2], CompilationError::UnclosedBlock { tag, .. } if tag == "for"));
+                assert_eq!(errors.len(), 3);
+                assert_eq!(output, Some("?yz?".to_string()));
+            }
+            other => panic!("Expected multiple errors, got {:?}", other),
+        }
+
+        let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(HashMap::new()));
+        let result = compiler.compile(&template, Context::empty());
+        assert!(matches!(result, Err(CompilationError::UnexpectedBlockTag { opened_at: None, .. })));
+    }
+
+    #[test]
+    fn should_locate_collected_errors() {
+        let template = Template::from("{{ a }}\n{{ b }}".to_string()).with_name("errors");
+        let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(HashMap::new()))
+            .with_error_mode(ErrorMode::Collect { placeholder: None });
+
+        let error = template.locate(compiler.compile(&template, Context::empty()).unwrap_err());
+
+        assert_eq!(error.to_string(), "found 2 error(s)\n\nunknown symbol `a`\n --> errors:1:4\n  |\n1 | {{ a }}\n  |    ^\n\nunknown symbol `b`\n --> errors:2:4\n  |\n2 | {{ b }}\n  |    ^");
+    }
+
+    #[test]
+    fn should_locate_errors_in_template() {
+        let template = Template::from("first line\n\t<%  if present %>{{ x }}<% missing %><% end %>".to_string())
+            .with_syntax(Syntax::new("<%", "%>"))
+            .with_name("page.html");
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+        let compiler = TemplateCompiler::new(engine);
+        let mut context = Context::empty();
+        context.set_variable("present", true);
+
+        let error = template.locate(compiler.compile(&template, context).unwrap_err());
+
+        assert_eq!(error.error.offset(), Some(39));
+        assert_eq!(error.to_string(), "unknown symbol `missing`\n --> page.html:2:29\n  |\n2 | \t<%  if present %>{{ x }}<% missing %><% end %>\n  | \t                           ^");
+
+        let template = Template::from("{{ if a }}\n{{ else }}\n{{ else }}".to_string());
+        let error = template.locate(compiler.compile(&template, Context::empty()).unwrap_err());
+
+        assert_eq!(error.to_string(), "unexpected `else` tag in the current block\n --> 3:1\n  |\n3 | {{ else }}\n  | ^");
+    }
 }
\ No newline at end of file
@@ -0,0 +1,1065 @@
+//! Document-level AST of a [`Template`](crate::simple::template::Template)
+//!
+//! A [Document] is a tree of text, expressions and blocks that is produced once by parsing a template
+//! and then walked by compilers. Blocks allow to emit text and code conditionally or repeatedly:
+//! ```text
+//! {{ if condition }} ... {{ else if other_condition }} ... {{ else }} ... {{ end }}
+//! {{ for item in items }} ... {{ else }} ... {{ end }}
+//! ```
+//!
+//! The escaping of code results can be changed for a region of the template:
+//! ```text
+//! {{ escape "json" }} ... {{ end }}
+//! ```
+//!
+//! A code slice is a block tag when its first identifier is `if`, `for`, `else`, `escape` or `end` and is not wrapped in parenthesis.
+//!
+//! Whitespace around code can be removed with trim markers, a `-` after the start delimiter
+//! or before the end delimiter (separated from the code with whitespace):
+//! ```text
+//! {{- name }}  removes whitespace (including newlines) before the code
+//! {{ name -}}  removes whitespace (including newlines) after the code
+//! ```
+//! Lines that contain only a block tag (or a comment) can be removed with [ParseOptions::trim_block_lines].
+//!
+//! Comments (`{{# ... #}}`) are kept in the document, but produce no output.
+//!
+//! [Document::parse_recovering] continues past malformed blocks and reports all of them,
+//! the malformed blocks are replaced with [DocumentNode::Invalid].
+
+use rubble_templates_core::ast::{SyntaxNode, ParseError, Literal};
+use rubble_templates_core::compiler::CompilationError;
+use rubble_templates_core::evaluator::{SyntaxError, EvaluationError};
+use rubble_templates_core::template::TemplateSlice;
+use rubble_templates_core::units::{Position, Span};
+use crate::parser::{parse_ast_with_pipes, desugar_pipes};
+use crate::simple::template::{Template, Syntax};
+
+/// A parsed template, ready to be compiled.
+///
+/// ```
+/// use rubble_templates_evaluators::simple::template::Template;
+/// use rubble_templates_evaluators::simple::document::{Document, DocumentNode};
+/// use rubble_templates_core::units::Span;
+///
+/// let template = Template::from("Hello {{ if name }}{{ name }}{{ end }}".to_string());
+/// let document = Document::parse(&template).unwrap();
+///
+/// assert_eq!(document.nodes.len(), 2);
+/// assert_eq!(document.nodes[0], DocumentNode::Text { value: "Hello ".to_string(), span: Span::new(0, 6) });
+/// assert_eq!(document.nodes[1].span(), Span::new(6, 38));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    pub nodes: Vec<DocumentNode>,
+}
+
+/// A node of a [Document].
+#[derive(Clone, Debug, PartialEq)]
+pub enum DocumentNode {
+    /// Raw text that is emitted as it is.
+    Text {
+        value: String,
+        span: Span,
+    },
+    /// Code that is evaluated and its result is emitted.
+    Expression(CodeBlock),
+    /// `{{ if }} ... {{ else if }} ... {{ else }} ... {{ end }}` block.
+    ///
+    /// The body of the first branch with a true condition is emitted, or `otherwise` if there is none.
+    Conditional {
+        branches: Vec<ConditionalBranch>,
+        otherwise: Vec<DocumentNode>,
+        span: Span,
+    },
+    /// `{{ for item in collection }} ... {{ else }} ... {{ end }}` block.
+    ///
+    /// The body is emitted for each item of the collection, or `otherwise` if the collection is empty.
+    /// Contains one variable name (item) or two variable names (key/index and item).
+    Loop {
+        variables: Vec<String>,
+        collection: CodeBlock,
+        body: Vec<DocumentNode>,
+        otherwise: Vec<DocumentNode>,
+        span: Span,
+    },
+    /// `{{ escape "name" }} ... {{ end }}` block.
+    ///
+    /// Results of code in the body are escaped with an escaper of the given name instead of the default one.
+    Escape {
+        escaper: String,
+        tag: CodeBlock,
+        body: Vec<DocumentNode>,
+        span: Span,
+    },
+    /// `{{# ... #}}` comment, the value contains the text between the comment delimiters.
+    Comment {
+        value: String,
+        span: Span,
+    },
+    /// A code block or a whole block that failed to parse, with the error that was found.
+    ///
+    /// Used only in documents parsed with [Document::parse_recovering], in place of the malformed part of template.
+    Invalid {
+        error: CompilationError,
+        span: Span,
+    },
+}
+
+impl DocumentNode {
+    /// Returns the fragment of template that this node was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            DocumentNode::Text { span, .. } => *span,
+            DocumentNode::Expression(code) => code.span,
+            DocumentNode::Conditional { span, .. } => *span,
+            DocumentNode::Loop { span, .. } => *span,
+            DocumentNode::Escape { span, .. } => *span,
+            DocumentNode::Comment { span, .. } => *span,
+            DocumentNode::Invalid { span, .. } => *span,
+        }
+    }
+}
+
+/// A branch of a conditional block, its body is emitted when the condition is true.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConditionalBranch {
+    pub condition: CodeBlock,
+    pub body: Vec<DocumentNode>,
+}
+
+/// A parsed code slice with its source.
+///
+/// For block tags, `ast` contains only the evaluated part (eg. the condition of `{{ if }}`),
+/// while `source` and `span` describe the whole tag.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeBlock {
+    pub ast: SyntaxNode,
+    pub source: String,
+    pub span: Span,
+    /// Offset of the code in `source` (the length of the start delimiter), positions in `ast` are relative to it.
+    pub code_start: usize,
+}
+
+impl CodeBlock {
+    /// Returns the absolute position of this code block in template.
+    pub fn position(&self) -> Position {
+        Position::Absolute(self.span.start)
+    }
+
+    /// Converts a position relative to the code start (eg. of a node in `ast`) to an absolute position in template.
+    /// Other positions are returned unchanged.
+    pub fn absolute(&self, position: &Position) -> Position {
+        match position {
+            Position::RelativeToCodeStart(offset) => Position::Absolute(self.span.start + self.code_start + offset),
+            other => other.clone(),
+        }
+    }
+
+    /// Creates a [CompilationError] of an evaluation of this code block, with positions of the error resolved to absolute.
+    pub fn evaluation_failed(&self, error: SyntaxError) -> CompilationError {
+        CompilationError::EvaluationFailed {
+            error: Box::new(SyntaxError {
+                relative_pos: self.absolute(&error.relative_pos),
+                invocation_pos: self.absolute(&error.invocation_pos),
+                description: error.description,
+            }),
+            position: self.position(),
+            source: self.source.clone(),
+        }
+    }
+}
+
+enum TagKind {
+    Expression,
+    If(SyntaxNode),
+    For(Vec<String>, SyntaxNode),
+    Escape(String),
+    ElseIf(SyntaxNode),
+    Else,
+    End,
+}
+
+/// Parsed nodes and the tag that terminated them (if it was not the end of template).
+type Sequence = (Vec<DocumentNode>, Option<(TagKind, CodeBlock)>);
+
+/// Options of parsing a [Document].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ParseOptions {
+    /// Syntax used for templates that do not have their own [Syntax], the default syntax is used if it is not set.
+    pub syntax: Option<Syntax>,
+    /// Removes lines that contain only a block tag (or a comment) and whitespace, including the line break.
+    ///
+    /// Useful for line-oriented output, eg. YAML:
+    /// ```text
+    /// items:
+    ///   {{ for item in items }}
+    ///   - {{ item }}
+    ///   {{ end }}
+    /// ```
+    pub trim_block_lines: bool,
+    /// Treats a code block without the end delimiter (and the rest of the template) as text,
+    /// instead of returning [CompilationError::UnterminatedBlock].
+    pub unterminated_as_text: bool,
+}
+
+impl Document {
+    /// Parses a [Template] into a [Document].
+    ///
+    /// Returns an error if blocks are unbalanced or misnested, code is malformed or a code block is not terminated.
+    pub fn parse(template: &Template) -> Result<Document, CompilationError> {
+        Document::parse_with(template, &ParseOptions::default())
+    }
+
+    /// Parses a [Template] into a [Document] using the given [ParseOptions].
+    ///
+    /// The [Syntax] of the template takes precedence over the syntax from options.
+    pub fn parse_with(template: &Template, options: &ParseOptions) -> Result<Document, CompilationError> {
+        let slices = template.iter_with(options.syntax.as_ref());
+        let options = ParseOptions {
+            syntax: Some(slices.syntax.clone()),
+            ..options.clone()
+        };
+        Document::from_slices(slices, &options)
+    }
+
+    /// Parses a [Template] into a [Document], continuing past errors.
+    ///
+    /// Returns the document and all errors that were found, in the order of [Document::parse_with].
+    /// Malformed code blocks, and whole blocks with malformed or unbalanced tags, are replaced with [DocumentNode::Invalid].
+    ///
+    /// ```
+    /// use rubble_templates_evaluators::simple::template::Template;
+    /// use rubble_templates_evaluators::simple::document::{Document, DocumentNode, ParseOptions};
+    ///
+    /// let template = Template::from("{{ (a }} {{ name }} {{ end }}".to_string());
+    /// let (document, errors) = Document::parse_recovering(&template, &ParseOptions::default());
+    ///
+    /// assert_eq!(errors.len(), 2);
+    /// assert!(matches!(document.nodes[0], DocumentNode::Invalid { .. }));
+    /// assert!(matches!(document.nodes[2], DocumentNode::Expression(_)));
+    /// ```
+    pub fn parse_recovering(template: &Template, options: &ParseOptions) -> (Document, Vec<CompilationError>) {
+        let slices = template.iter_with(options.syntax.as_ref());
+        let options = ParseOptions {
+            syntax: Some(slices.syntax.clone()),
+            ..options.clone()
+        };
+        parse_slices(slices, &options)
+    }
+
+    /// Parses [TemplateSlice]s into a [Document].
+    ///
+    /// The syntax from [ParseOptions] has to match the delimiters of the code slices.
+    pub fn from_slices<'a, I>(slices: I, options: &ParseOptions) -> Result<Document, CompilationError>
+        where I: IntoIterator<Item=TemplateSlice<'a>> {
+        let (document, errors) = parse_slices(slices, options);
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(document),
+        }
+    }
+}
+
+/// Parses slices into a document, recovering from errors, see [Document::parse_recovering].
+fn parse_slices<'a, I>(slices: I, options: &ParseOptions) -> (Document, Vec<CompilationError>)
+    where I: IntoIterator<Item=TemplateSlice<'a>> {
+    let mut errors = vec![];
+    let mut tokens = tokenize(slices, options, &mut errors).into_iter();
+    let mut nodes = vec![];
+
+    loop {
+        let (sequence, terminator) = parse_sequence(&mut tokens, &mut errors);
+        nodes.extend(sequence);
+        match terminator {
+            Some((_, tag)) => nodes.push(invalid(unexpected_tag(&tag, None), tag.span, &mut errors)),
+            None => break,
+        }
+    }
+
+    (Document { nodes }, errors)
+}
+
+/// A template slice with parsed code and whitespace control applied.
+enum Token {
+    Text {
+        value: String,
+        span: Span,
+    },
+    Code(CodeBlock),
+    Comment {
+        value: String,
+        span: Span,
+    },
+    Invalid {
+        error: CompilationError,
+        span: Span,
+    },
+}
+
+/// A template slice before whitespace control is applied.
+enum RawToken<'a> {
+    Text {
+        value: &'a str,
+        start: usize,
+        keep: (usize, usize),
+    },
+    Code {
+        code: CodeBlock,
+        trim_before: bool,
+        trim_after: bool,
+    },
+    Comment {
+        value: String,
+        span: Span,
+    },
+    Invalid {
+        error: CompilationError,
+        span: Span,
+    },
+}
+
+impl RawToken<'_> {
+    /// Returns the text of this token, if it is a text before another token in the same line.
+    fn line_before(&self, first: bool) -> Option<&str> {
+        match self {
+            RawToken::Text { value, .. } => match value.rfind('\n') {
+                Some(index) => Some(&value[(index + 1)..]),
+                None if first => Some(value),
+                None => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the text of this token, if it is a text after another token in the same line.
+    fn line_after(&self, last: bool) -> Option<&str> {
+        match self {
+            RawToken::Text { value, .. } => match value.find('\n') {
+                Some(index) => Some(&value[..index]),
+                None if last => Some(value),
+                None => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn keep(&mut self, start: Option<usize>, end: Option<usize>) {
+        if let RawToken::Text { keep, .. } = self {
+            keep.0 = keep.0.max(start.unwrap_or(0));
+            keep.1 = keep.1.min(end.unwrap_or(keep.1));
+        }
+    }
+}
+
+/// Parses code of slices and applies whitespace control to the text.
+///
+/// Code that fails to parse is replaced with [Token::Invalid] and its error is added to `errors`.
+fn tokenize<'a, I>(slices: I, options: &ParseOptions, errors: &mut Vec<CompilationError>) -> Vec<Token>
+    where I: IntoIterator<Item=TemplateSlice<'a>> {
+    let syntax = options.syntax.clone().unwrap_or_default();
+    let mut tokens: Vec<RawToken> = slices.into_iter()
+        .map(|slice| match slice {
+            TemplateSlice::Text { value, start_position, .. } => RawToken::Text {
+                value,
+                start: start_position,
+                keep: (0, value.len()),
+            },
+            TemplateSlice::Code { value, start_position, end_position } => {
+                let (code, trim_before, trim_after) = strip_trim_markers(value, &syntax);
+                let code_start = syntax.code_start().len();
+                let span = Span::new(start_position, end_position);
+                let ast = parse_ast_with_pipes(&code, syntax.code_start(), syntax.code_end())
+                    .map_err(|error| CompilationError::ParseFailed {
+                        error: ParseError {
+                            position: match error.position {
+                                Position::RelativeToCodeStart(offset) => Position::Absolute(start_position + code_start + offset),
+                                other => other,
+                            },
+                            ..error
+                        },
+                        position: Position::Absolute(start_position),
+                        source: value.to_string(),
+                    });
+
+                match ast {
+                    Ok(ast) => RawToken::Code {
+                        code: CodeBlock {
+                            ast,
+                            source: value.to_string(),
+                            span,
+                            code_start,
+                        },
+                        trim_before,
+                        trim_after,
+                    },
+                    Err(error) => invalid_token(error, span, errors),
+                }
+            }
+            TemplateSlice::Comment { value, start_position, end_position } => RawToken::Comment {
+                value: comment_of(value, &syntax),
+                span: Span::new(start_position, end_position),
+            },
+            TemplateSlice::Unterminated { value, start_position, .. } if options.unterminated_as_text => RawToken::Text {
+                value,
+                start: start_position,
+                keep: (0, value.len()),
+            },
+            TemplateSlice::Unterminated { value, start_position, end_position } => invalid_token(
+                CompilationError::UnterminatedBlock {
+                    position: Position::Absolute(start_position),
+                    source: value.lines().next().unwrap_or_default().to_string(),
+                },
+                Span::new(start_position, end_position),
+                errors,
+            ),
+        })
+        .collect();
+
+    for index in 0..tokens.len() {
+        let (trim_before, trim_after, block_tag) = match &tokens[index] {
+            RawToken::Code { code, trim_before, trim_after } => (*trim_before, *trim_after, is_block_tag(&code.ast)),
+            RawToken::Comment { .. } => (false, false, true),
+            RawToken::Text { .. } | RawToken::Invalid { .. } => continue,
+        };
+        let last = tokens.len() - 1;
+
+        let standalone = options.trim_block_lines && block_tag
+            && (index == 0 || tokens[index - 1].line_before(index == 1).map(is_blank).unwrap_or(false))
+            && (index == last || tokens[index + 1].line_after(index + 1 == last).map(is_blank).unwrap_or(false));
+
+        if index > 0 {
+            let previous = &mut tokens[index - 1];
+            if let RawToken::Text { value, .. } = previous {
+                let end = if trim_before {
+                    Some(value.trim_end().len())
+                } else if standalone {
+                    Some(value.rfind('\n').map(|it| it + 1).unwrap_or(0))
+                } else {
+                    None
+                };
+                previous.keep(None, end);
+            }
+        }
+
+        if index < last {
+            let next = &mut tokens[index + 1];
+            if let RawToken::Text { value, .. } = next {
+                let start = if trim_after {
+                    Some(value.len() - value.trim_start().len())
+                } else if standalone {
+                    Some(value.find('\n').map(|it| it + 1).unwrap_or(value.len()))
+                } else {
+                    None
+                };
+                next.keep(start, None);
+            }
+        }
+    }
+
+    tokens.into_iter()
+        .filter_map(|token| match token {
+            RawToken::Text { value, start, keep: (keep_start, keep_end) } if keep_start < keep_end => Some(Token::Text {
+                value: value[keep_start..keep_end].to_string(),
+                span: Span::new(start + keep_start, start + keep_end),
+            }),
+            RawToken::Text { .. } => None,
+            RawToken::Code { code, .. } => Some(Token::Code(code)),
+            RawToken::Comment { value, span } => Some(Token::Comment { value, span }),
+            RawToken::Invalid { error, span } => Some(Token::Invalid { error, span }),
+        })
+        .collect()
+}
+
+fn invalid_token<'a>(error: CompilationError, span: Span, errors: &mut Vec<CompilationError>) -> RawToken<'a> {
+    errors.push(error.clone());
+    RawToken::Invalid { error, span }
+}
+
+fn is_blank(text: &str) -> bool {
+    text.chars().all(char::is_whitespace)
+}
+
+fn is_block_tag(ast: &SyntaxNode) -> bool {
+    matches!(ast, SyntaxNode::NamedNode { identifier, .. } if ["if", "for", "else", "escape", "end"].contains(&identifier.as_str()))
+}
+
+/// Returns the text of a comment without its delimiters.
+fn comment_of(source: &str, syntax: &Syntax) -> String {
+    let start = format!("{}#", syntax.code_start());
+    let end = format!("#{}", syntax.code_end());
+    let value = source.strip_prefix(&start).unwrap_or(source);
+    value.strip_suffix(&end).unwrap_or(value).to_string()
+}
+
+/// Replaces trim markers with spaces, so positions in code do not change.
+///
+/// Returns the code and whether whitespace before and after the code should be removed.
+fn strip_trim_markers(source: &str, syntax: &Syntax) -> (String, bool, bool) {
+    let start = syntax.code_start().len();
+    let end = source.len().saturating_sub(syntax.code_end().len());
+    if start >= end || !source.starts_with(syntax.code_start()) || !source.ends_with(syntax.code_end()) {
+        return (source.to_string(), false, false);
+    }
+
+    let code = &source[start..end];
+    let trim_before = code.len() > 1 && code.starts_with('-') && code[1..].starts_with(char::is_whitespace);
+    let trim_after = code.len() > 1 && code.ends_with('-') && code[..(code.len() - 1)].ends_with(char::is_whitespace);
+
+    let mut result = source.to_string();
+    if trim_before {
+        result.replace_range(start..(start + 1), " ");
+    }
+    if trim_after {
+        result.replace_range((end - 1)..end, " ");
+    }
+
+    (result, trim_before, trim_after)
+}
+
+/// Parses nodes until the end of template or until a tag that closes (or continues) the current block.
+///
+/// Errors are added to `errors` and the malformed parts of template are replaced with [DocumentNode::Invalid].
+fn parse_sequence<I>(tokens: &mut I, errors: &mut Vec<CompilationError>) -> Sequence
+    where I: Iterator<Item=Token> {
+    let mut nodes = vec![];
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Text { value, span } => nodes.push(DocumentNode::Text { value, span }),
+            Token::Comment { value, span } => nodes.push(DocumentNode::Comment { value, span }),
+            Token::Invalid { error, span } => nodes.push(DocumentNode::Invalid { error, span }),
+            Token::Code(tag) => match classify(&tag) {
+                Ok(TagKind::Expression) => nodes.push(DocumentNode::Expression(CodeBlock { ast: desugar_pipes(tag.ast), ..tag })),
+                Ok(TagKind::If(condition)) => nodes.push(parse_conditional(tokens, condition, tag, errors)),
+                Ok(TagKind::For(variables, collection)) => nodes.push(parse_loop(tokens, variables, collection, tag, errors)),
+                Ok(TagKind::Escape(escaper)) => nodes.push(parse_escape(tokens, escaper, tag, errors)),
+                Ok(kind) => return (nodes, Some((kind, tag))),
+                Err(error) => {
+                    errors.push(error.clone());
+                    // malformed tags still open or close blocks, so the rest of template is parsed as if they were valid
+                    match tag_name(&tag.ast) {
+                        "if" | "for" | "escape" => nodes.push(skip_block(tokens, error, tag, errors)),
+                        "else" => return (nodes, Some((TagKind::Else, tag))),
+                        "end" => return (nodes, Some((TagKind::End, tag))),
+                        _ => nodes.push(DocumentNode::Invalid { error, span: tag.span }),
+                    }
+                }
+            },
+        }
+    }
+
+    (nodes, None)
+}
+
+fn parse_conditional<I>(tokens: &mut I, condition: SyntaxNode, opening: CodeBlock, errors: &mut Vec<CompilationError>) -> DocumentNode
+    where I: Iterator<Item=Token> {
+    let opened_at = opening.position();
+    let start = opening.span.start;
+    let mut branches = vec![];
+    let mut current = Some(CodeBlock { ast: condition, ..opening });
+    let mut otherwise: Option<Vec<DocumentNode>> = None;
+
+    let end = loop {
+        let (body, terminator) = parse_sequence(tokens, errors);
+
+        match current.take() {
+            Some(condition) => branches.push(ConditionalBranch { condition, body }),
+            None => otherwise.get_or_insert_with(Vec::new).extend(body),
+        }
+
+        match terminator {
+            None => {
+                let first: &ConditionalBranch = &branches[0];
+                let end = end_of(&first.condition, branches.iter().flat_map(|branch| &branch.body).chain(otherwise.iter().flatten()));
+                return invalid(unclosed_block("if", &first.condition), Span::new(start, end), errors);
+            }
+            Some((TagKind::End, tag)) => break tag.span.end,
+            Some((TagKind::ElseIf(condition), tag)) if otherwise.is_none() =>
+                current = Some(CodeBlock { ast: condition, ..tag }),
+            Some((TagKind::Else, _)) if otherwise.is_none() => otherwise = Some(vec![]),
+            // the unexpected tag is skipped and the following nodes are a part of the current branch
+            Some((_, tag)) => errors.push(unexpected_tag(&tag, Some(opened_at.clone()))),
+        }
+    };
+
+    DocumentNode::Conditional {
+        branches,
+        otherwise: otherwise.unwrap_or_default(),
+        span: Span::new(start, end),
+    }
+}
+
+fn parse_loop<I>(tokens: &mut I, variables: Vec<String>, collection: SyntaxNode, opening: CodeBlock, errors: &mut Vec<CompilationError>) -> DocumentNode
+    where I: Iterator<Item=Token> {
+    let opened_at = opening.position();
+    let collection = CodeBlock { ast: collection, ..opening };
+    let mut body = vec![];
+    let mut otherwise: Option<Vec<DocumentNode>> = None;
+
+    let end = loop {
+        let (nodes, terminator) = parse_sequence(tokens, errors);
+        match &mut otherwise {
+            Some(otherwise) => otherwise.extend(nodes),
+            None => body.extend(nodes),
+        }
+
+        match terminator {
+            None => {
+                let end = end_of(&collection, body.iter().chain(otherwise.iter().flatten()));
+                return invalid(unclosed_block("for", &collection), Span::new(collection.span.start, end), errors);
+            }
+            Some((TagKind::End, tag)) => break tag.span.end,
+            Some((TagKind::Else, _)) if otherwise.is_none() => otherwise = Some(vec![]),
+            Some((_, tag)) => errors.push(unexpected_tag(&tag, Some(opened_at.clone()))),
+        }
+    };
+
+    let span = Span::new(collection.span.start, end);
+    DocumentNode::Loop {
+        variables,
+        collection,
+        body,
+        otherwise: otherwise.unwrap_or_default(),
+        span,
+    }
+}
+
+fn parse_escape<I>(tokens: &mut I, escaper: String, opening: CodeBlock, errors: &mut Vec<CompilationError>) -> DocumentNode
+    where I: Iterator<Item=Token> {
+    let mut body = vec![];
+
+    let end = loop {
+        let (nodes, terminator) = parse_sequence(tokens, errors);
+        body.extend(nodes);
+
+        match terminator {
+            None => {
+                let end = end_of(&opening, body.iter());
+                return invalid(unclosed_block("escape", &opening), Span::new(opening.span.start, end), errors);
+            }
+            Some((TagKind::End, tag)) => break tag.span.end,
+            Some((_, tag)) => errors.push(unexpected_tag(&tag, Some(opening.position()))),
+        }
+    };
+
+    let span = Span::new(opening.span.start, end);
+    DocumentNode::Escape {
+        escaper,
+        tag: opening,
+        body,
+        span,
+    }
+}
+
+/// Parses a block with a malformed opening tag, the whole block (up to its `end` tag) is replaced with [DocumentNode::Invalid].
+///
+/// The body is still parsed to find its errors and the matching `end` tag.
+fn skip_block<I>(tokens: &mut I, error: CompilationError, opening: CodeBlock, errors: &mut Vec<CompilationError>) -> DocumentNode
+    where I: Iterator<Item=Token> {
+    let mut end = opening.span.end;
+    loop {
+        let (nodes, terminator) = parse_sequence(tokens, errors);
+        end = nodes.last().map(|node| node.span().end).unwrap_or(end);
+
+        match terminator {
+            None => break,
+            Some((TagKind::End, tag)) => {
+                end = tag.span.end;
+                break;
+            }
+            // `else` tags belong to the malformed block
+            Some((_, tag)) => end = tag.span.end,
+        }
+    }
+
+    DocumentNode::Invalid {
+        error,
+        span: Span::new(opening.span.start, end),
+    }
+}
+
+/// Records an error and creates a node that replaces the malformed part of template.
+fn invalid(error: CompilationError, span: Span, errors: &mut Vec<CompilationError>) -> DocumentNode {
+    errors.push(error.clone());
+    DocumentNode::Invalid { error, span }
+}
+
+/// Returns the end of an unclosed block, that is the end of its last node.
+fn end_of<'a, I>(opening: &CodeBlock, nodes: I) -> usize where I: Iterator<Item=&'a DocumentNode> {
+    nodes.last().map(|node| node.span().end).unwrap_or(opening.span.end)
+}
+
+fn tag_name(ast: &SyntaxNode) -> &str {
+    match ast {
+        SyntaxNode::NamedNode { identifier, .. } => identifier,
+        SyntaxNode::AnonymousNode { .. } | SyntaxNode::Literal { .. } => "",
+    }
+}
+
+fn classify(tag: &CodeBlock) -> Result<TagKind, CompilationError> {
+    let (identifier, children) = match &tag.ast {
+        SyntaxNode::NamedNode { identifier, children, .. } => (identifier.as_str(), children),
+        _ => return Ok(TagKind::Expression),
+    };
+
+    match identifier {
+        "if" => condition_of(tag, children).map(TagKind::If),
+        "for" => loop_of(tag, children),
+        "escape" => escaper_of(tag, children).map(TagKind::Escape),
+        "else" if children.is_empty() => Ok(TagKind::Else),
+        "else" => match &children[0] {
+            SyntaxNode::NamedNode { identifier, children: if_children, .. } if identifier == "if" && if_children.is_empty() =>
+                condition_of(tag, &children[1..]).map(TagKind::ElseIf),
+            _ => Err(unexpected_tag(tag, None)),
+        },
+        "end" if children.is_empty() => Ok(TagKind::End),
+        "end" => Err(unexpected_tag(tag, None)),
+        _ => Ok(TagKind::Expression),
+    }
+}
+
+/// Creates a condition from arguments of a block tag.
+///
+/// A single argument is used as it is, multiple arguments are treated as a function call,
+/// so `if > count 0` is the same as `if (> count 0)`. Pipes apply to the whole condition,
+/// so `if name | trim` is the same as `if (trim name)`.
+fn condition_of(tag: &CodeBlock, arguments: &[SyntaxNode]) -> Result<SyntaxNode, CompilationError> {
+    match arguments {
+        [] => Err(tag.evaluation_failed(SyntaxError::new(EvaluationError::InvalidArguments {
+            description: Some("Block tag requires a condition".to_string()),
+            arguments: vec![],
+        }))),
+        [single] => Ok(desugar_pipes(single.clone())),
+        [SyntaxNode::NamedNode { identifier, starts_at, children }, rest @ ..] if children.is_empty() =>
+            Ok(desugar_pipes(SyntaxNode::NamedNode {
+                identifier: identifier.clone(),
+                starts_at: starts_at.clone(),
+                children: rest.to_vec(),
+            })),
+        _ => Ok(desugar_pipes(SyntaxNode::AnonymousNode {
+            starts_at: arguments[0].starts_at().clone(),
+            children: arguments.to_vec(),
+        })),
+    }
+}
+
+/// Reads loop variables and the collection from arguments of a `for` tag.
+///
+/// Expects `for item in collection` or `for key, value in collection`.
+fn loop_of(tag: &CodeBlock, arguments: &[SyntaxNode]) -> Result<TagKind, CompilationError> {
+    let invalid = || tag.evaluation_failed(SyntaxError::new(EvaluationError::InvalidArguments {
+        description: Some("Expected `for item in collection` or `for key, value in collection`".to_string()),
+        arguments: arguments.to_vec(),
+    }));
+
+    let separator = arguments.iter()
+        .position(|argument| matches!(argument, SyntaxNode::NamedNode { identifier, children, .. } if identifier == "in" && children.is_empty()))
+        .ok_or_else(invalid)?;
+
+    // commas are a part of words, so `key, value`, `key ,value` and `key,value` are split into names and commas here
+    let mut parts: Vec<&str> = vec![];
+    for argument in &arguments[..separator] {
+        match argument {
+            SyntaxNode::NamedNode { identifier, children, .. } if children.is_empty() => {
+                for (index, name) in identifier.split(',').enumerate() {
+                    if index > 0 {
+                        parts.push(",");
+                    }
+                    if !name.is_empty() {
+                        parts.push(name);
+                    }
+                }
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    let variables = match parts.as_slice() {
+        [name] => vec![name.to_string()],
+        [key, ",", value] => vec![key.to_string(), value.to_string()],
+        _ => return Err(invalid()),
+    };
+    if variables.iter().any(|name| name == "," || name.contains(['.', '[', '"'])) {
+        return Err(invalid());
+    }
+
+    let collection = condition_of(tag, &arguments[(separator + 1)..])?;
+    Ok(TagKind::For(variables, collection))
+}
+
+/// Reads the escaper name from arguments of an `escape` tag, the name can be quoted or not.
+fn escaper_of(tag: &CodeBlock, arguments: &[SyntaxNode]) -> Result<String, CompilationError> {
+    match arguments {
+        [SyntaxNode::NamedNode { identifier: name, children, .. }] if children.is_empty() => Ok(name.clone()),
+        [SyntaxNode::Literal { value: Literal::String(name), .. }] => Ok(name.clone()),
+        _ => Err(tag.evaluation_failed(SyntaxError::new(EvaluationError::InvalidArguments {
+            description: Some("Expected `escape \"name\"`".to_string()),
+            arguments: arguments.to_vec(),
+        }))),
+    }
+}
+
+fn unclosed_block(name: &str, opening: &CodeBlock) -> CompilationError {
+    CompilationError::UnclosedBlock {
+        tag: name.to_string(),
+        position: opening.position(),
+        source: opening.source.clone(),
+    }
+}
+
+fn unexpected_tag(tag: &CodeBlock, opened_at: Option<Position>) -> CompilationError {
+    CompilationError::UnexpectedBlockTag {
+        tag: tag_name(&tag.ast).to_string(),
+        position: tag.position(),
+        source: tag.source.clone(),
+        opened_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rubble_templates_core::ast::{SyntaxNode, ParseError, ParseErrorKind};
+    use rubble_templates_core::compiler::CompilationError;
+    use rubble_templates_core::units::{Position, Span};
+    use crate::simple::document::{Document, DocumentNode, ParseOptions};
+    use crate::simple::template::{Template, Syntax};
+
+    fn text(value: &str, start: usize) -> DocumentNode {
+        DocumentNode::Text {
+            value: value.to_string(),
+            span: Span::new(start, start + value.len()),
+        }
+    }
+
+    #[test]
+    fn should_parse_conditional_blocks() {
+        let template = Template::from("a{{ if x }}b{{ else if y }}c{{ else }}d{{ end }}e".to_string());
+        let nodes = Document::parse(&template).unwrap().nodes;
+
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0], text("a", 0));
+        assert_eq!(nodes[2], text("e", 48));
+        if let DocumentNode::Conditional { branches, otherwise, span } = &nodes[1] {
+            assert_eq!(span, &Span::new(1, 48));
+            assert_eq!(branches.len(), 2);
+            assert_eq!(branches[0].condition.span, Span::new(1, 11));
+            assert_eq!(branches[0].body, vec![text("b", 11)]);
+            assert_eq!(branches[1].condition.source, "{{ else if y }}");
+            assert_eq!(branches[1].body, vec![text("c", 27)]);
+            assert_eq!(otherwise, &vec![text("d", 38)]);
+        } else {
+            panic!("Expected conditional block, got {:?}", nodes[1]);
+        }
+    }
+
+    #[test]
+    fn should_parse_loop_blocks() {
+        let template = Template::from("{{ for key, value in map }}{{ key }}{{ else }}empty{{ end }}".to_string());
+        let nodes = Document::parse(&template).unwrap().nodes;
+
+        assert_eq!(nodes.len(), 1);
+        if let DocumentNode::Loop { variables, collection, body, otherwise, span } = &nodes[0] {
+            assert_eq!(variables, &vec!["key".to_string(), "value".to_string()]);
+            assert_eq!(collection.source, "{{ for key, value in map }}");
+            assert_eq!(body.len(), 1);
+            assert_eq!(body[0].span(), Span::new(27, 36));
+            assert_eq!(otherwise, &vec![text("empty", 46)]);
+            assert_eq!(span, &Span::new(0, 60));
+        } else {
+            panic!("Expected loop block, got {:?}", nodes[0]);
+        }
+
+        for source in &["{{ for key,value in map }}", "{{ for key ,value in map }}", "{{ for key , value in map }}"] {
+            let template = Template::from(format!("{}{{{{ end }}}}", source));
+            let nodes = Document::parse(&template).unwrap().nodes;
+            assert!(matches!(&nodes[0], DocumentNode::Loop { variables, .. } if variables == &vec!["key".to_string(), "value".to_string()]), "{}", source);
+        }
+    }
+
+    #[test]
+    fn should_report_malformed_loop_tags() {
+        let sources = [
+            "{{ for item items }}",
+            "{{ for a b in x }}",
+            "{{ for a, in x }}",
+            "{{ for , a in x }}",
+            "{{ for a,, b in x }}",
+            "{{ for a, b, c in x }}",
+            "{{ for a.b in x }}",
+            "{{ for in x }}",
+        ];
+
+        for source in sources.iter() {
+            let template = Template::from(format!("{}{{{{ end }}}}", source));
+            assert!(matches!(
+                Document::parse(&template),
+                Err(CompilationError::EvaluationFailed { source: error_source, .. }) if error_source == *source
+            ), "{}", source);
+        }
+    }
+
+    #[test]
+    fn should_report_unclosed_block() {
+        let template = Template::from("text {{ if x }} {{ if y }} {{ end }}".to_string());
+
+        assert_eq!(Document::parse(&template), Err(CompilationError::UnclosedBlock {
+            tag: "if".to_string(),
+            position: Position::Absolute(5),
+            source: "{{ if x }}".to_string(),
+        }));
+    }
+
+    #[test]
+    fn should_report_unexpected_tags() {
+        let template = Template::from("text {{ end }}".to_string());
+        assert_eq!(Document::parse(&template), Err(CompilationError::UnexpectedBlockTag {
+            tag: "end".to_string(),
+            position: Position::Absolute(5),
+            source: "{{ end }}".to_string(),
+            opened_at: None,
+        }));
+
+        let template = Template::from("{{ for x in y }}{{ else if y }}{{ end }}".to_string());
+        assert_eq!(Document::parse(&template), Err(CompilationError::UnexpectedBlockTag {
+            tag: "else".to_string(),
+            position: Position::Absolute(16),
+            source: "{{ else if y }}".to_string(),
+            opened_at: Some(Position::Absolute(0)),
+        }));
+
+        let template = Template::from("{{ if x }}{{ else }}{{ else if y }}{{ end }}".to_string());
+        assert_eq!(Document::parse(&template), Err(CompilationError::UnexpectedBlockTag {
+            tag: "else".to_string(),
+            position: Position::Absolute(20),
+            source: "{{ else if y }}".to_string(),
+            opened_at: Some(Position::Absolute(0)),
+        }));
+    }
+
+    #[test]
+    fn should_report_unterminated_block() {
+        let template = Template::from("a: {{ a }}\nb: {{ b }\nc: c".to_string());
+        assert_eq!(Document::parse(&template), Err(CompilationError::UnterminatedBlock {
+            position: Position::Absolute(14),
+            source: "{{ b }".to_string(),
+        }));
+
+        let options = ParseOptions {
+            unterminated_as_text: true,
+            ..ParseOptions::default()
+        };
+        let nodes = Document::parse_with(&template, &options).unwrap().nodes;
+        assert!(matches!(&nodes[..], [
+            DocumentNode::Text { .. },
+            DocumentNode::Expression(_),
+            DocumentNode::Text { .. },
+            DocumentNode::Text { value, span: Span { start: 14, end: 25 } },
+        ] if value == "{{ b }\nc: c"));
+    }
+
+    #[test]
+    fn should_report_malformed_code() {
+        let template = Template::from("text {{ if (a }}{{ end }}".to_string());
+        assert_eq!(Document::parse(&template), Err(CompilationError::ParseFailed {
+            error: ParseError::new(Position::Absolute(11), ParseErrorKind::UnclosedParenthesis),
+            position: Position::Absolute(5),
+            source: "{{ if (a }}".to_string(),
+        }));
+
+        let template = Template::from("line\n<% plus 1 2) %>".to_string()).with_syntax(Syntax::new("<%", "%>"));
+        let error = template.locate(Document::parse(&template).unwrap_err());
+        assert_eq!(error.to_string(), "unexpected `)`\n --> 2:12\n  |\n2 | <% plus 1 2) %>\n  |            ^");
+    }
+
+    #[test]
+    fn should_trim_whitespace_around_markers() {
+        let template = Template::from("a \n {{- x -}} \n b {{ y -}}\n\n{{-z}} {{-}} c".to_string());
+        let nodes = Document::parse(&template).unwrap().nodes;
+
+        assert_eq!(nodes[0], text("a", 0));
+        assert_eq!(nodes[1].span(), Span::new(4, 13));
+        assert_eq!(nodes[2], text("b ", 16));
+        assert_eq!(nodes[3].span(), Span::new(18, 26));
+        assert_eq!(nodes[4].span(), Span::new(28, 34));
+        assert_eq!(nodes[5], text(" ", 34));
+        assert_eq!(nodes[6].span(), Span::new(35, 40));
+        assert_eq!(nodes[7], text(" c", 40));
+        assert_eq!(nodes.len(), 8);
+
+        if let DocumentNode::Expression(code) = &nodes[1] {
+            assert_eq!(code.source, "{{- x -}}");
+            assert!(matches!(&code.ast, SyntaxNode::NamedNode { identifier, children, .. } if identifier == "x" && children.is_empty()));
+        } else {
+            panic!("Expected expression, got {:?}", nodes[1]);
+        }
+    }
+
+    #[test]
+    fn should_trim_block_lines() {
+        let template = Template::from("items:\n  {{ for item in items }}\n  - {{ item }}\n  {{ end }}\ndone {{ if x }}\n{{ end }}".to_string());
+        let options = ParseOptions {
+            trim_block_lines: true,
+            ..ParseOptions::default()
+        };
+        let nodes = Document::parse_with(&template, &options).unwrap().nodes;
+
+        assert_eq!(nodes[0], text("items:\n", 0));
+        if let DocumentNode::Loop { body, .. } = &nodes[1] {
+            assert_eq!(body[0], text("  - ", 33));
+            assert_eq!(body[2], text("\n", 47));
+            assert_eq!(body.len(), 3);
+        } else {
+            panic!("Expected loop block, got {:?}", nodes[1]);
+        }
+        assert_eq!(nodes[2], text("done ", 60));
+        if let DocumentNode::Conditional { branches, .. } = &nodes[3] {
+            assert_eq!(branches[0].body, vec![text("\n", 75)]);
+        } else {
+            panic!("Expected conditional block, got {:?}", nodes[3]);
+        }
+        assert_eq!(nodes.len(), 4);
+
+        let nodes = Document::parse(&template).unwrap().nodes;
+        assert_eq!(nodes[0], text("items:\n  ", 0));
+    }
+
+    #[test]
+    fn should_keep_comments() {
+        let template = Template::from("{{ if x }}\n  {{# multiline\n  comment }} #}}\n{{ end }}".to_string());
+        let options = ParseOptions {
+            trim_block_lines: true,
+            ..ParseOptions::default()
+        };
+        let nodes = Document::parse_with(&template, &options).unwrap().nodes;
+
+        if let DocumentNode::Conditional { branches, .. } = &nodes[0] {
+            assert_eq!(branches[0].body, vec![DocumentNode::Comment {
+                value: " multiline\n  comment }} ".to_string(),
+                span: Span::new(13, 43),
+            }]);
+        } else {
+            panic!("Expected conditional block, got {:?}", nodes[0]);
+        }
+    }
+
+    #[test]
+    fn should_parse_escape_blocks() {
+        let template = Template::from("{{ escape \"json\" }}{{ a }}{{ escape url }}{{ b }}{{ end }}{{ end }}".to_string());
+        let nodes = Document::parse(&template).unwrap().nodes;
+
+        if let DocumentNode::Escape { escaper, body, span, .. } = &nodes[0] {
+            assert_eq!(escaper, "json");
+            assert_eq!(span, &Span::new(0, 67));
+            assert!(matches!(&body[1], DocumentNode::Escape { escaper, .. } if escaper == "url"));
+        } else {
+            panic!("Expected escape block, got {:?}", nodes[0]);
+        }
+
+        let template = Template::from("{{ escape }}{{ end }}".to_string());
+        assert!(matches!(Document::parse(&template), Err(CompilationError::EvaluationFailed { .. })));
+
+        let template = Template::from("{{ escape html }}{{ else }}{{ end }}".to_string());
+        assert!(matches!(Document::parse(&template), Err(CompilationError::UnexpectedBlockTag { .. })));
+    }
+}
@@ -0,0 +1,180 @@
+//! Escaping of code block results, used to safely emit values in HTML, XML, JSON, YAML, shell scripts or URLs
+
+use rubble_templates_core::escaper::Escaper;
+use rubble_templates_core::value::Value;
+
+/// Escaping policy of a [`TemplateCompiler`](crate::simple::compiler::TemplateCompiler).
+///
+/// Every code block result is escaped, except for [Value::Safe] values
+/// (eg. returned by the `safe` or `raw` standard functions).
+///
+/// ```
+/// use rubble_templates_core::escaper::Escaper;
+/// use rubble_templates_evaluators::simple::escape::Escaping;
+///
+/// assert_eq!(Escaping::Html.escape("<a href=\"#\">Tom & Jerry's</a>"), "&lt;a href=&quot;#&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;");
+/// assert_eq!(Escaping::Xml.escape("'a' & \"b\""), "&apos;a&apos; &amp; &quot;b&quot;");
+/// assert_eq!(Escaping::Json.escape("say \"hi\"\n"), "\"say \\\"hi\\\"\\n\"");
+/// assert_eq!(Escaping::Yaml.escape("key: value"), "\"key: value\"");
+/// assert_eq!(Escaping::Shell.escape("it's $HOME"), "'it'\\''s $HOME'");
+/// assert_eq!(Escaping::Url.escape("a b&c/ż"), "a%20b%26c%2F%C5%BC");
+/// assert_eq!(Escaping::None.escape("<b>"), "<b>");
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Escaping {
+    /// Results are emitted as they are.
+    #[default]
+    None,
+    /// Escapes `&`, `<`, `>`, `"` and `'`, so results can be used in HTML text and quoted attributes.
+    Html,
+    /// Escapes `&`, `<`, `>`, `"` and `'` with XML entities.
+    Xml,
+    /// Emits results as JSON string literals (including the quotes).
+    Json,
+    /// Emits results as double-quoted YAML scalars (including the quotes).
+    Yaml,
+    /// Emits results as single-quoted POSIX shell words.
+    Shell,
+    /// Percent-encodes results, so they can be used as URL components (eg. path segments or query parameters).
+    Url,
+}
+
+impl Escaping {
+    /// All escaping policies, as they are named in `{{ escape "name" }}` blocks.
+    pub const NAMED: [(&'static str, Escaping); 7] = [
+        ("none", Escaping::None),
+        ("html", Escaping::Html),
+        ("xml", Escaping::Xml),
+        ("json", Escaping::Json),
+        ("yaml", Escaping::Yaml),
+        ("shell", Escaping::Shell),
+        ("url", Escaping::Url),
+    ];
+}
+
+impl Escaper for Escaping {
+    fn escape(&self, text: &str) -> String {
+        match self {
+            Escaping::None => text.to_string(),
+            Escaping::Html => escape_markup(text, "&#39;"),
+            Escaping::Xml => escape_markup(text, "&apos;"),
+            Escaping::Json => escape_json(text),
+            Escaping::Yaml => escape_yaml(text),
+            Escaping::Shell => format!("'{}'", text.replace('\'', "'\\''")),
+            Escaping::Url => escape_url(text),
+        }
+    }
+}
+
+/// Converts a value to text that can be emitted, [Value::Safe] values are not escaped.
+pub fn escape_value(escaper: &dyn Escaper, value: &Value) -> String {
+    match value {
+        Value::Safe(text) => text.clone(),
+        other => escaper.escape(&other.to_string()),
+    }
+}
+
+fn escape_markup(text: &str, apostrophe: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str(apostrophe),
+            other => result.push(other),
+        }
+    }
+    result
+}
+
+fn escape_json(text: &str) -> String {
+    let mut result = String::with_capacity(text.len() + 2);
+    result.push('"');
+    for char in text.chars() {
+        match char {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\u{8}' => result.push_str("\\b"),
+            '\u{c}' => result.push_str("\\f"),
+            other if other.is_control() => result.push_str(&format!("\\u{:04x}", other as u32)),
+            other => result.push(other),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn escape_yaml(text: &str) -> String {
+    let mut result = String::with_capacity(text.len() + 2);
+    result.push('"');
+    for char in text.chars() {
+        match char {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\0' => result.push_str("\\0"),
+            other if other.is_control() => result.push_str(&format!("\\u{:04x}", other as u32)),
+            other => result.push(other),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn escape_url(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => result.push(byte as char),
+            other => result.push_str(&format!("%{:02X}", other)),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use rubble_templates_core::escaper::Escaper;
+    use rubble_templates_core::value::Value;
+    use crate::simple::escape::{Escaping, escape_value};
+
+    #[test]
+    fn should_escape_json_strings() {
+        assert_eq!(Escaping::Json.escape(""), "\"\"");
+        assert_eq!(Escaping::Json.escape("a\\b\t\u{1}ż"), "\"a\\\\b\\t\\u0001ż\"");
+    }
+
+    #[test]
+    fn should_escape_yaml_scalars() {
+        assert_eq!(Escaping::Yaml.escape("- item # not a comment"), "\"- item # not a comment\"");
+        assert_eq!(Escaping::Yaml.escape("yes"), "\"yes\"");
+        assert_eq!(Escaping::Yaml.escape("\"quoted\"\n\\"), "\"\\\"quoted\\\"\\n\\\\\"");
+    }
+
+    #[test]
+    fn should_escape_shell_words() {
+        assert_eq!(Escaping::Shell.escape(""), "''");
+        assert_eq!(Escaping::Shell.escape("$(rm -rf /); `id`"), "'$(rm -rf /); `id`'");
+        assert_eq!(Escaping::Shell.escape("'"), "''\\'''");
+    }
+
+    #[test]
+    fn should_escape_url_components() {
+        assert_eq!(Escaping::Url.escape("AZaz09-_.~"), "AZaz09-_.~");
+        assert_eq!(Escaping::Url.escape("?q=1&r=#"), "%3Fq%3D1%26r%3D%23");
+    }
+
+    #[test]
+    fn should_not_escape_safe_values() {
+        assert_eq!(escape_value(&Escaping::Html, &Value::Safe("<b>".to_string())), "<b>");
+        assert_eq!(escape_value(&Escaping::Html, &Value::from("<b>")), "&lt;b&gt;");
+        assert_eq!(escape_value(&Escaping::Json, &Value::Integer(1)), "\"1\"");
+    }
+}
@@ -4,12 +4,16 @@ use std::collections::HashMap;
 use rubble_templates_core::evaluator::{Function, Context, SyntaxError, Evaluator, EvaluationError};
 use rubble_templates_core::units::Position;
 use rubble_templates_core::ast::SyntaxNode;
+use rubble_templates_core::value::Value;
+use crate::simple::path::{parse_path, get_segment, PathSegment};
 
 /// Simple evaluation engine providing basic features like variable and function evaluation.
 ///
-/// This engine evaluates are variables, functions and literals in a code fragment and returns
-/// a resulting String. It might also return an error when encounters unexpected identifier or
-/// a variable with parameters. When it encounters a function, it tries to evaluate it and
+/// This engine evaluates all variables, functions and literals in a code fragment and returns
+/// a resulting [Value]. It might also return an error when encounters unexpected identifier or
+/// a variable with parameters.
+///
+/// Nested variables can be accessed with paths, eg. `user.address.city`, `items.0.name` or `map["key with space"]`. When it encounters a function, it tries to evaluate it and
 /// propagates any error that it returns.
 ///
 /// This engine always uses the same Functions for evaluations, but can be supplied with different
@@ -25,27 +29,24 @@ impl SimpleEvaluationEngine {
         }
     }
 
-    fn evaluate_symbol(&self, context: &mut Context, identifier: &str, offset: Position, parameters: &[SyntaxNode]) -> Result<String, SyntaxError> {
-        match context.get_variable(identifier).cloned() {
-            Some(result) => Ok(result),
-            None => self.evaluate_function_or_literal(identifier, offset, parameters, context),
+    fn evaluate_symbol(&self, context: &mut Context, identifier: &str, offset: Position, parameters: &[SyntaxNode]) -> Result<Value, SyntaxError> {
+        if let Some(result) = context.get_variable(identifier) {
+            return Ok(result.clone());
         }
-    }
 
-    fn evaluate_function_or_literal(&self, identifier: &str, offset: Position, parameters: &[SyntaxNode], context: &mut Context) -> Result<String, SyntaxError> {
-        self.evaluate_function(identifier, offset.clone(), parameters, context)
-            .unwrap_or_else(|| {
-                extract_literal(identifier)
-                    .map(|it| it.to_string())
-                    .ok_or_else(|| SyntaxError::at_position(offset, EvaluationError::UnknownSymbol {
-                        symbol: identifier.to_string(),
-                    }))
-            })
+        match parse_path(identifier) {
+            Some(segments) if context.get_variable(&segments[0].name).is_some() =>
+                resolve_path(context, identifier, &segments, offset),
+            _ => self.evaluate_function(identifier, offset.clone(), parameters, context)
+                .unwrap_or_else(|| Err(SyntaxError::at_position(offset, EvaluationError::UnknownSymbol {
+                    symbol: identifier.to_string(),
+                }))),
+        }
     }
 
-    fn evaluate_function(&self, identifier: &str, offset: Position, parameters: &[SyntaxNode], context: &mut Context) -> Option<Result<String, SyntaxError>> {
+    fn evaluate_function(&self, identifier: &str, offset: Position, parameters: &[SyntaxNode], context: &mut Context) -> Option<Result<Value, SyntaxError>> {
         Some(self.functions.get(identifier)?
-            .evaluate(self as &dyn Evaluator, &parameters, context)
+            .evaluate(self as &dyn Evaluator, parameters, context)
             .map_err(|mut err| {
                 err.invocation_pos = offset;
                 err
@@ -55,28 +56,30 @@ impl SimpleEvaluationEngine {
 }
 
 impl Evaluator for SimpleEvaluationEngine {
-    fn evaluate(&self, syntax_node: &SyntaxNode, context: &mut Context) -> Result<String, SyntaxError> {
+    fn evaluate(&self, syntax_node: &SyntaxNode, context: &mut Context) -> Result<Value, SyntaxError> {
         evaluate(syntax_node, |identifier, offset, parameters|
             self.evaluate_symbol(context, identifier, offset, parameters),
         )
     }
 }
 
-fn evaluate<E>(syntax_node: &SyntaxNode, mut evaluate_symbol: E) -> Result<String, SyntaxError>
-    where E: FnMut(&str, Position, &[SyntaxNode]) -> Result<String, SyntaxError> {
+fn evaluate<E>(syntax_node: &SyntaxNode, mut evaluate_symbol: E) -> Result<Value, SyntaxError>
+    where E: FnMut(&str, Position, &[SyntaxNode]) -> Result<Value, SyntaxError> {
     match syntax_node {
         SyntaxNode::NamedNode { identifier, children, starts_at } =>
             evaluate_symbol(identifier.as_str(), starts_at.clone(), children),
 
         SyntaxNode::AnonymousNode { children, starts_at } =>
             evaluate_nested(starts_at.clone(), children, evaluate_symbol),
+
+        SyntaxNode::Literal { value, .. } => Ok(Value::from(value)),
     }
 }
 
-fn evaluate_nested<E>(offset: Position, children: &[SyntaxNode], mut evaluate_symbol: E) -> Result<String, SyntaxError>
-    where E: FnMut(&str, Position, &[SyntaxNode]) -> Result<String, SyntaxError> {
+fn evaluate_nested<E>(offset: Position, children: &[SyntaxNode], mut evaluate_symbol: E) -> Result<Value, SyntaxError>
+    where E: FnMut(&str, Position, &[SyntaxNode]) -> Result<Value, SyntaxError> {
     if children.is_empty() {
-        return Result::Ok("".to_string());
+        return Result::Ok(Value::Null);
     }
 
     let child = children[0].clone();
@@ -92,22 +95,29 @@ fn evaluate_nested<E>(offset: Position, children: &[SyntaxNode], mut evaluate_sy
     }
 }
 
-fn extract_literal(source: &str) -> Option<&str> {
-    if source.parse::<f64>().is_ok() {
-        Some(source)
-    } else if source.starts_with('"') && source.ends_with('"') && source.len() > 2 {
-        Some(&source[1..(source.len() - 1)])
-    } else {
-        None
+fn resolve_path(context: &Context, path: &str, segments: &[PathSegment], offset: Position) -> Result<Value, SyntaxError> {
+    let mut value = context.get_variable(&segments[0].name);
+
+    for segment in &segments[1..] {
+        value = value.and_then(|it| get_segment(it, &segment.name));
+        if value.is_none() {
+            return Err(SyntaxError::at_position(offset.shifted(segment.offset), EvaluationError::UnknownPathSegment {
+                path: path.to_string(),
+                segment: segment.name.clone(),
+            }));
+        }
     }
+
+    Ok(value.cloned().unwrap_or(Value::Null))
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-    use rubble_templates_core::ast::SyntaxNode;
+    use std::collections::{HashMap, BTreeMap};
+    use rubble_templates_core::ast::{SyntaxNode, Literal};
     use rubble_templates_core::evaluator::{Evaluator, Function, EvaluationError, Context, SyntaxError};
     use rubble_templates_core::units::Position;
+    use rubble_templates_core::value::Value;
     use crate::simple::evaluator::SimpleEvaluationEngine;
 
     #[test]
@@ -120,15 +130,63 @@ mod tests {
         // variable subsitution
 
         let result = engine.evaluate(&node_of("variable"), &mut context);
-        assert_eq!(result.ok(), Some("1234".to_string()));
+        assert_eq!(result.ok(), Some(Value::String("1234".to_string())));
+    }
+
+    #[test]
+    fn should_evaluate_literals() {
+        let mut context = Context::empty();
+        // variables cannot shadow literals
+        context.set_variable("42", "variable");
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
 
-        // number literal
-        let result = engine.evaluate(&node_of("-12.2"), &mut context);
-        assert_eq!(result.ok(), Some("-12.2".to_string()));
+        let literals = [
+            (Literal::Float(-12.2), Value::Float(-12.2)),
+            (Literal::Integer(42), Value::Integer(42)),
+            (Literal::String("test".to_string()), Value::String("test".to_string())),
+            (Literal::Boolean(true), Value::Bool(true)),
+        ];
 
-        // string literal
-        let result = engine.evaluate(&node_of("\"test\""), &mut context);
-        assert_eq!(result.ok(), Some("test".to_string()));
+        for (literal, expected) in literals.iter().cloned() {
+            let node = SyntaxNode::AnonymousNode {
+                starts_at: Position::RelativeToCodeStart(0),
+                children: vec![SyntaxNode::Literal { value: literal, starts_at: Position::RelativeToCodeStart(1) }],
+            };
+            assert_eq!(engine.evaluate(&node, &mut context), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn should_evaluate_path() {
+        let mut address = BTreeMap::new();
+        address.insert("city".to_string(), Value::from("Warsaw"));
+        let mut user = BTreeMap::new();
+        user.insert("address".to_string(), Value::Map(address));
+        user.insert("tags".to_string(), Value::from(vec!["admin", "owner"]));
+        let mut context = Context::empty();
+        context.set_variable("user", Value::Map(user));
+        context.set_variable("flat.key", "flat");
+        let engine = SimpleEvaluationEngine::from(HashMap::new());
+
+        let result = engine.evaluate(&node_of("user.address.city"), &mut context);
+        assert_eq!(result.ok(), Some(Value::from("Warsaw")));
+
+        let result = engine.evaluate(&node_of("user[\"tags\"].1"), &mut context);
+        assert_eq!(result.ok(), Some(Value::from("owner")));
+
+        // variables with dots in names take precedence
+        let result = engine.evaluate(&node_of("flat.key"), &mut context);
+        assert_eq!(result.ok(), Some(Value::from("flat")));
+
+        let result = engine.evaluate(&node_of("user.address.zip"), &mut context);
+        assert_eq!(result.err(), Some(SyntaxError {
+            relative_pos: Position::RelativeToCodeStart(23),
+            invocation_pos: Position::Unknown,
+            description: EvaluationError::UnknownPathSegment {
+                path: "user.address.zip".to_string(),
+                segment: "zip".to_string(),
+            },
+        }));
     }
 
     #[test]
@@ -139,8 +197,8 @@ mod tests {
         // using closure as function in the evaluation engine
         let function =
             |_evaluator: &dyn Evaluator, parameters: &[SyntaxNode], _context: &mut Context| {
-                if let Some(SyntaxNode::NamedNode { identifier, .. }) = parameters.get(0) {
-                    Result::Ok(identifier.clone())
+                if let Some(SyntaxNode::NamedNode { identifier, .. }) = parameters.first() {
+                    Result::Ok(Value::String(identifier.clone()))
                 } else {
                     Result::Err(SyntaxError::new(EvaluationError::InvalidArguments {
                         description: None,
@@ -167,7 +225,7 @@ mod tests {
 
         // correct function call
         let result = engine.evaluate(&node, &mut context);
-        assert_eq!(result.ok(), Some("param".to_string()));
+        assert_eq!(result.ok(), Some(Value::String("param".to_string())));
 
         // incorrect function call
         let result = engine.evaluate(&node_of("our_function"), &mut context);
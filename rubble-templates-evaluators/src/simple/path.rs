@@ -0,0 +1,198 @@
+//! Resolution of paths to nested variables, eg. `user.address.city`, `items.0.name` or `map["key with space"]`
+
+use rubble_templates_core::value::Value;
+use crate::lexer::unescape_string;
+
+/// A single segment of a variable path.
+///
+/// Contains the name of the accessed key (or index) and the offset of the segment in the path,
+/// so errors can point to the exact segment that could not be resolved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathSegment {
+    pub name: String,
+    pub offset: usize,
+}
+
+/// Splits an identifier into path segments.
+///
+/// Segments are separated with dots (`user.address.city`) or written in brackets (`items[0]`, `map["key with space"]`).
+/// Returns `None` if the identifier is not a path (it contains only one segment) or if it is malformed.
+///
+/// ```
+/// use rubble_templates_evaluators::simple::path::{parse_path, PathSegment};
+///
+/// let segments = parse_path("map[\"some key\"].value").unwrap();
+/// let names: Vec<&str> = segments.iter().map(|it| it.name.as_str()).collect();
+///
+/// assert_eq!(names, vec!["map", "some key", "value"]);
+/// assert_eq!(segments[2].offset, 16);
+/// ```
+pub fn parse_path(identifier: &str) -> Option<Vec<PathSegment>> {
+    let mut segments = vec![];
+    let mut current = String::new();
+    let mut current_offset = 0;
+    let mut after_dot = false;
+    let mut after_bracket = false;
+    let mut chars = identifier.char_indices().peekable();
+
+    while let Some((offset, char)) = chars.next() {
+        match char {
+            '.' => {
+                if !current.is_empty() {
+                    segments.push(PathSegment { name: current.clone(), offset: current_offset });
+                    current.clear();
+                } else if !after_bracket {
+                    return None;
+                }
+                current_offset = offset + 1;
+                after_dot = true;
+                after_bracket = false;
+            }
+            '[' => {
+                if !current.is_empty() {
+                    segments.push(PathSegment { name: current.clone(), offset: current_offset });
+                    current.clear();
+                } else if after_dot || segments.is_empty() {
+                    return None;
+                }
+
+                let (segment, end) = parse_bracket(identifier, offset)?;
+                segments.push(segment);
+                while chars.peek().map(|(index, _)| *index < end).unwrap_or(false) {
+                    chars.next();
+                }
+                current_offset = end;
+                after_dot = false;
+                after_bracket = true;
+            }
+            _ => {
+                if after_bracket {
+                    return None;
+                }
+                current.push(char);
+                after_dot = false;
+            }
+        }
+    }
+
+    if after_dot {
+        return None;
+    }
+    if !current.is_empty() {
+        segments.push(PathSegment { name: current, offset: current_offset });
+    }
+
+    if segments.len() > 1 {
+        Some(segments)
+    } else {
+        None
+    }
+}
+
+/// Parses `[0]` or `["key"]` starting at `start` and returns the segment and the offset right after `]`.
+///
+/// Escapes in quoted keys are replaced like in string literals, eg. `["a\"b"]` is the key `a"b`.
+fn parse_bracket(identifier: &str, start: usize) -> Option<(PathSegment, usize)> {
+    let remainder = &identifier[(start + 1)..];
+
+    if let Some(quoted) = remainder.strip_prefix('"') {
+        let closing = find_closing_quote(quoted)?;
+        if !quoted[(closing + 1)..].starts_with(']') {
+            return None;
+        }
+        let name = unescape_string(&quoted[..closing]);
+        Some((PathSegment { name, offset: start + 2 }, start + closing + 4))
+    } else {
+        let closing = remainder.find(']')?;
+        let name = remainder[..closing].trim();
+        if name.is_empty() {
+            return None;
+        }
+        Some((PathSegment { name: name.to_string(), offset: start + 1 }, start + closing + 2))
+    }
+}
+
+/// Finds the quote that ends a string literal, skipping escaped characters.
+fn find_closing_quote(quoted: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (index, char) in quoted.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Looks up a segment in a given value.
+///
+/// Maps are accessed by key and lists are accessed by index.
+/// Returns `None` if there is no such key or index, or if the value is not a map or list.
+pub fn get_segment<'a>(value: &'a Value, segment: &str) -> Option<&'a Value> {
+    match value {
+        Value::Map(map) => map.get(segment),
+        Value::List(list) => list.get(segment.parse::<usize>().ok()?),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use rubble_templates_core::value::Value;
+    use crate::simple::path::{parse_path, PathSegment, get_segment};
+
+    #[test]
+    fn should_parse_path() {
+        let expected = vec![
+            PathSegment { name: "items".to_string(), offset: 0 },
+            PathSegment { name: "0".to_string(), offset: 6 },
+            PathSegment { name: "name".to_string(), offset: 8 },
+        ];
+        assert_eq!(parse_path("items.0.name"), Some(expected));
+
+        let expected = vec![
+            PathSegment { name: "items".to_string(), offset: 0 },
+            PathSegment { name: "1".to_string(), offset: 6 },
+            PathSegment { name: "key with space".to_string(), offset: 10 },
+        ];
+        assert_eq!(parse_path("items[1][\"key with space\"]"), Some(expected));
+    }
+
+    #[test]
+    fn should_unescape_quoted_keys() {
+        let expected = vec![
+            PathSegment { name: "map".to_string(), offset: 0 },
+            PathSegment { name: "a\"b\\".to_string(), offset: 5 },
+            PathSegment { name: "c".to_string(), offset: 14 },
+        ];
+        assert_eq!(parse_path(r#"map["a\"b\\"].c"#), Some(expected));
+        assert_eq!(parse_path(r#"map["a\"]"#), None);
+    }
+
+    #[test]
+    fn should_reject_non_paths() {
+        assert_eq!(parse_path("variable"), None);
+        assert_eq!(parse_path("a..b"), None);
+        assert_eq!(parse_path(".a"), None);
+        assert_eq!(parse_path("a."), None);
+        assert_eq!(parse_path("a[\"b"), None);
+        assert_eq!(parse_path("a[]"), None);
+    }
+
+    #[test]
+    fn should_get_segment() {
+        let mut map = BTreeMap::new();
+        map.insert("key".to_string(), Value::from("value"));
+        let map = Value::Map(map);
+        let list = Value::from(vec![1, 2]);
+
+        assert_eq!(get_segment(&map, "key"), Some(&Value::from("value")));
+        assert_eq!(get_segment(&map, "other"), None);
+        assert_eq!(get_segment(&list, "1"), Some(&Value::Integer(2)));
+        assert_eq!(get_segment(&list, "2"), None);
+        assert_eq!(get_segment(&Value::from("text"), "0"), None);
+    }
+}
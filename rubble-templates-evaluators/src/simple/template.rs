@@ -1,37 +1,159 @@
 //! Simple representation of text template that will be evaluated
 //!
-//! Code blocks are indicated by {{ and }}.
-//! After matching "{{", it does not check whether the code is actually completed,
-//! it looks for the first match of "}}" to complete the code block.
+//! Code blocks are indicated by {{ and }} by default, other delimiters can be configured with [Syntax].
+//! After matching the start delimiter, it does not check whether the code is actually completed,
+//! it looks for the first match of the end delimiter outside of string literals to complete the code block.
 //!
-//! To use "}}" or " (double quotes) in code, please use standard functions
-//! (see [`std_functions`](rubble-templates::std_fun::std_functions)
-//! and [`string_functions`](rubble-templates::std_fun::std_functions::strings::string_functions) for more info).
+//! To use the start delimiter in raw text, escape it with a backslash, eg. `\{{` is emitted as `{{`.
+//! A backslash right before a code block has to be escaped too, eg. `\\{{ code }}` is emitted as `\` and the result of the code.
+//! Each pair of backslashes before the start delimiter is emitted as a single backslash, and an odd one escapes the delimiter.
+//!
+//! Comments are indicated by `#` after the start delimiter and before the end delimiter, eg. `{{# comment #}}`.
+//! They can span multiple lines and contain the end delimiter, as they end with the first match of `#` and the end delimiter.
+//! String literals in code can contain the end delimiter and escaped characters, eg. `{{ "}} \" \\" }}`.
 
 use std::{fs, io};
 use std::path::Path;
 use rubble_templates_core::template::{TemplateSlice, EvaluableMixedContent};
+use rubble_templates_core::compiler::{CompilationError, TemplateError};
+
+/// Delimiters that indicate code blocks in a [Template].
+///
+/// Delimiters can have multiple characters and do not need to be symmetric:
+/// ```
+/// use rubble_templates_core::template::TemplateSlice;
+/// use rubble_templates_evaluators::simple::template::{Template, Syntax};
+///
+/// let template = Template::from("{{ not code }} <% code %>".to_string())
+///     .with_syntax(Syntax::new("<%", "%>"));
+/// let slices: Vec<TemplateSlice> = template.into_iter().collect();
+///
+/// assert_eq!(slices[1], TemplateSlice::Code {
+///     value: "<% code %>",
+///     start_position: 15,
+///     end_position: 25,
+/// });
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Syntax {
+    code_start: String,
+    code_end: String,
+}
+
+impl Syntax {
+    /// Creates syntax with the given code delimiters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the delimiters is empty.
+    pub fn new(code_start: &str, code_end: &str) -> Syntax {
+        assert!(!code_start.is_empty() && !code_end.is_empty(), "Code delimiters cannot be empty");
+        Syntax {
+            code_start: code_start.to_string(),
+            code_end: code_end.to_string(),
+        }
+    }
+
+    pub fn code_start(&self) -> &str {
+        &self.code_start
+    }
+
+    pub fn code_end(&self) -> &str {
+        &self.code_end
+    }
+}
+
+/// The default syntax, with code blocks indicated by `{{` and `}}`.
+impl Default for Syntax {
+    fn default() -> Self {
+        Syntax::new(START_PATTERN, END_PATTERN)
+    }
+}
 
 /// A simple template that represents a source text.
 ///
 /// This template is a raw template that can be reused with different variables or Evaluators.
 /// It contains a raw source that can be parsed.
+///
+/// The [Syntax] of a template takes precedence over the syntax of a compiler.
+/// If none of them is set, the default syntax is used.
+///
+/// The name of a template (eg. a file name) is used to describe errors, see [Template::locate].
 #[derive(Debug, Eq, PartialEq)]
 pub struct Template {
-    pub raw_content: String
+    pub raw_content: String,
+    pub syntax: Option<Syntax>,
+    pub name: Option<String>,
 }
 
 impl Template {
+    /// Reads a template from file, the path of the file is used as the name of the template.
     pub fn read_from(path: &Path) -> Result<Template, io::Error> {
         let raw_content = fs::read_to_string(path)?;
-        Ok(Template {
-            raw_content
-        })
+        Ok(Template::from(raw_content).with_name(&path.display().to_string()))
     }
 
     pub fn from(raw_content: String) -> Template {
         Template {
-            raw_content
+            raw_content,
+            syntax: None,
+            name: None,
+        }
+    }
+
+    /// Sets the name of this template.
+    pub fn with_name(self, name: &str) -> Template {
+        Template {
+            name: Some(name.to_string()),
+            ..self
+        }
+    }
+
+    /// Locates an error returned for this template, so it can be displayed with the line, column and a fragment of the template.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use rubble_templates_core::compiler::Compiler;
+    /// use rubble_templates_core::evaluator::Context;
+    /// use rubble_templates_evaluators::simple::template::Template;
+    /// use rubble_templates_evaluators::simple::evaluator::SimpleEvaluationEngine;
+    /// use rubble_templates_evaluators::simple::compiler::TemplateCompiler;
+    ///
+    /// let template = Template::from("Hello,\n{{ user }}!".to_string()).with_name("greeting");
+    /// let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(HashMap::new()));
+    ///
+    /// let error = template.locate(compiler.compile(&template, Context::empty()).unwrap_err());
+    ///
+    /// assert_eq!(error.to_string(), "\
+    /// unknown symbol `user`
+    ///  --> greeting:2:4
+    ///   |
+    /// 2 | {{ user }}!
+    ///   |    ^");
+    /// ```
+    pub fn locate(&self, error: CompilationError) -> TemplateError {
+        TemplateError::new(error, &self.raw_content, self.name.as_deref())
+    }
+
+    /// Sets the code delimiters of this template.
+    pub fn with_syntax(self, syntax: Syntax) -> Template {
+        Template {
+            syntax: Some(syntax),
+            ..self
+        }
+    }
+
+    /// Returns an iterator that uses the syntax of this template, or the given syntax if the template has none.
+    pub fn iter_with(&self, syntax: Option<&Syntax>) -> EvaluableMixedContentIterator<'_, Template> {
+        let syntax = self.syntax.as_ref()
+            .or(syntax)
+            .cloned()
+            .unwrap_or_default();
+
+        EvaluableMixedContentIterator {
+            source: self,
+            current_position: 0,
+            syntax,
         }
     }
 }
@@ -44,27 +166,30 @@ impl Template {
 pub struct EvaluableMixedContentIterator<'a, T> {
     pub source: &'a T,
     pub current_position: usize,
+    pub syntax: Syntax,
 }
 
-impl<'a> EvaluableMixedContent for &'a Template {}
+impl EvaluableMixedContent for &Template {}
 
 impl<'a> IntoIterator for &'a Template {
     type Item = TemplateSlice<'a>;
     type IntoIter = EvaluableMixedContentIterator<'a, Template>;
 
     fn into_iter(self) -> Self::IntoIter {
-        EvaluableMixedContentIterator {
-            source: &self,
-            current_position: 0,
-        }
+        self.iter_with(None)
     }
 }
 
-pub(crate) const START_PATTERN: &str = "{{";
-pub(crate) const END_PATTERN: &str = "}}";
+const START_PATTERN: &str = "{{";
+const END_PATTERN: &str = "}}";
 
 /// Used to iterate over a template and extract all code blocks.
 ///
+/// Backslashes right before a start delimiter are escapes: each pair of them is emitted as a single backslash,
+/// and an odd backslash escapes the delimiter. An escaped delimiter is not a code block, it is returned as a separate text slice.
+/// For example, `\\{{ a }}` is a backslash and a code block, while `\\\{{ a }}` is a backslash and `{{ a }}` as text.
+/// A start delimiter without the end delimiter is returned with the rest of the template as [TemplateSlice::Unterminated].
+///
 /// ```
 /// use rubble_templates_core::template::{EvaluableMixedContent, TemplateSlice};
 /// use rubble_templates_evaluators::simple::template::Template;
@@ -93,31 +218,75 @@ impl<'a> Iterator for EvaluableMixedContentIterator<'a, Template> {
         let i = self.current_position;
         let raw_content = self.source.raw_content.as_str();
         let source_length = raw_content.len();
+        let code_start = self.syntax.code_start.as_str();
+        let code_end = self.syntax.code_end.as_str();
+
+        let start_position = match raw_content[i..].find(code_start) {
+            Some(start_position) => start_position + i,
+            None if i < source_length => {
+                self.current_position = source_length;
+
+                return Some(TemplateSlice::Text {
+                    value: &raw_content[i..],
+                    start_position: i,
+                    end_position: source_length,
+                });
+            }
+            None => return None,
+        };
+
+        // each pair of backslashes before a start delimiter is a single backslash,
+        // an odd backslash escapes the delimiter
+        let text_before = &raw_content[i..start_position];
+        let backslashes = (text_before.len() - text_before.trim_end_matches(ESCAPE).len()) / ESCAPE.len();
+        let escaped = backslashes % 2 == 1;
+        let text_end = start_position - (backslashes - backslashes / 2) * ESCAPE.len();
 
-        let start_position = raw_content[i..].find(START_PATTERN);
-        if start_position.is_none() && i < source_length {
-            self.current_position = source_length;
+        if i < text_end {
+            // the next call starts at the odd backslash (if there is one), so it returns the escaped delimiter
+            self.current_position = if escaped { start_position - ESCAPE.len() } else { start_position };
 
             return Some(TemplateSlice::Text {
-                value: &raw_content[i..],
+                value: &raw_content[i..text_end],
                 start_position: i,
-                end_position: source_length,
+                end_position: text_end,
             });
         }
 
-        let start_position = start_position? + i;
-        if i < start_position {
-            self.current_position = start_position;
+        if escaped {
+            let end_position = start_position + code_start.len();
+            self.current_position = end_position;
 
             return Some(TemplateSlice::Text {
-                value: &raw_content[i..start_position],
-                start_position: i,
-                end_position: start_position,
+                value: &raw_content[start_position..end_position],
+                start_position,
+                end_position,
+            });
+        }
+
+        let code_position = start_position + code_start.len();
+        if raw_content[code_position..].starts_with(COMMENT) {
+            let comment_end = format!("{}{}", COMMENT, code_end);
+            let comment_position = code_position + COMMENT.len();
+            let end_offset = match raw_content[comment_position..].find(&comment_end) {
+                Some(end_offset) => end_offset,
+                None => return Some(self.unterminated(start_position)),
+            };
+            let end_position = comment_position + end_offset + comment_end.len();
+            self.current_position = end_position;
+
+            return Some(TemplateSlice::Comment {
+                value: &raw_content[start_position..end_position],
+                start_position,
+                end_position,
             });
         }
 
-        let end_offset = raw_content[start_position..].find(END_PATTERN)?;
-        let end_position = start_position + end_offset + END_PATTERN.len();
+        let end_offset = match find_code_end(&raw_content[code_position..], code_end) {
+            Some(end_offset) => end_offset,
+            None => return Some(self.unterminated(start_position)),
+        };
+        let end_position = code_position + end_offset + code_end.len();
         self.current_position = end_position;
 
         Some(TemplateSlice::Code {
@@ -128,19 +297,72 @@ impl<'a> Iterator for EvaluableMixedContentIterator<'a, Template> {
     }
 }
 
+impl<'a> EvaluableMixedContentIterator<'a, Template> {
+    /// Returns the rest of the template as an unterminated block.
+    fn unterminated(&mut self, start_position: usize) -> TemplateSlice<'a> {
+        let raw_content = self.source.raw_content.as_str();
+        self.current_position = raw_content.len();
+
+        TemplateSlice::Unterminated {
+            value: &raw_content[start_position..],
+            start_position,
+            end_position: raw_content.len(),
+        }
+    }
+}
+
+const ESCAPE: &str = "\\";
+const COMMENT: &str = "#";
+
+/// Finds the first end delimiter in code that is not inside of a string literal.
+///
+/// If a string literal is never terminated, the first end delimiter is used (even if it is inside of the string),
+/// so the block ends where it was most likely meant to end and the parser can report the unterminated string.
+fn find_code_end(code: &str, code_end: &str) -> Option<usize> {
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut first_end = None;
+
+    for (index, char) in code.char_indices() {
+        if first_end.is_none() && code[index..].starts_with(code_end) {
+            first_end = Some(index);
+        }
+
+        if in_string {
+            match char {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if code[index..].starts_with(code_end) {
+            return Some(index);
+        } else if char == '"' {
+            in_string = true;
+        }
+    }
+
+    if in_string {
+        first_end
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
     use rubble_templates_core::template::TemplateSlice;
-    use crate::simple::template::Template;
+    use crate::simple::template::{Template, Syntax};
 
     #[test]
     fn should_create_template() {
         let path_buf = PathBuf::from("test-assets/simple-template");
         let result = Template::read_from(&path_buf);
 
-        let expected = Template::from("Some template {{ variable }} - or something".to_string());
+        let expected = Template::from("Some template {{ variable }} - or something".to_string())
+            .with_name("test-assets/simple-template");
 
         let result = result.map_err(|e| e.kind());
         assert_eq!(result, Ok(expected));
@@ -170,5 +392,237 @@ mod tests {
         ];
         assert_eq!(all_evaluation_spots, expected);
     }
-}
 
+    #[test]
+    fn should_find_evaluation_spots_with_custom_syntax() {
+        let template = Template::from("[[ a ]]{{ b }}[[c]]$ d $".to_string())
+            .with_syntax(Syntax::new("[[", "]]"));
+        let code: Vec<&str> = template.into_iter()
+            .filter_map(|slice| match slice {
+                TemplateSlice::Code { value, .. } => Some(value),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(code, vec!["[[ a ]]", "[[c]]"]);
+
+        let template = Template::from("$ a $ $$ $b$".to_string())
+            .with_syntax(Syntax::new("$", "$"));
+        let code: Vec<&str> = template.into_iter()
+            .filter_map(|slice| match slice {
+                TemplateSlice::Code { value, .. } => Some(value),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(code, vec!["$ a $", "$$", "$b$"]);
+    }
+
+    #[test]
+    fn should_prefer_template_syntax() {
+        let compiler_syntax = Syntax::new("<%", "%>");
+        let template = Template::from("<% a %>".to_string());
+        assert_eq!(template.iter_with(Some(&compiler_syntax)).syntax, compiler_syntax);
+
+        let template = template.with_syntax(Syntax::new("[[", "]]"));
+        assert_eq!(template.iter_with(Some(&compiler_syntax)).syntax, Syntax::new("[[", "]]"));
+        assert_eq!(template.iter_with(None).syntax, Syntax::new("[[", "]]"));
+        assert_eq!(Template::from("".to_string()).iter_with(None).syntax, Syntax::default());
+    }
+
+    #[test]
+    fn should_escape_code_start_in_text() {
+        let template = Template::from("a \\{{ b }} {{ c }}".to_string());
+        let slices: Vec<TemplateSlice> = template.into_iter().collect();
+        let expected = vec![
+            TemplateSlice::Text {
+                value: "a ",
+                start_position: 0,
+                end_position: 2,
+            },
+            TemplateSlice::Text {
+                value: "{{",
+                start_position: 3,
+                end_position: 5,
+            },
+            TemplateSlice::Text {
+                value: " b }} ",
+                start_position: 5,
+                end_position: 11,
+            },
+            TemplateSlice::Code {
+                value: "{{ c }}",
+                start_position: 11,
+                end_position: 18,
+            },
+        ];
+        assert_eq!(slices, expected);
+    }
+
+    #[test]
+    fn should_keep_escaped_backslash_before_code() {
+        let template = Template::from("C:\\\\{{ dir }} \\\\{{ a }}".to_string());
+        let slices: Vec<TemplateSlice> = template.into_iter().collect();
+        let expected = vec![
+            TemplateSlice::Text {
+                value: "C:\\",
+                start_position: 0,
+                end_position: 3,
+            },
+            TemplateSlice::Code {
+                value: "{{ dir }}",
+                start_position: 4,
+                end_position: 13,
+            },
+            TemplateSlice::Text {
+                value: " \\",
+                start_position: 13,
+                end_position: 15,
+            },
+            TemplateSlice::Code {
+                value: "{{ a }}",
+                start_position: 16,
+                end_position: 23,
+            },
+        ];
+        assert_eq!(slices, expected);
+
+        let template = Template::from("\\\\{{ a }}\\{{ b }}".to_string());
+        let slices: Vec<TemplateSlice> = template.into_iter().collect();
+        let values: Vec<&str> = slices.iter()
+            .map(|slice| match slice {
+                TemplateSlice::Text { value, .. } | TemplateSlice::Code { value, .. } => *value,
+                other => panic!("Unexpected slice {:?}", other),
+            })
+            .collect();
+        assert_eq!(values, vec!["\\", "{{ a }}", "{{", " b }}"]);
+    }
+
+    #[test]
+    fn should_pair_backslashes_before_code() {
+        let cases = [
+            ("\\\\\\{{ x }}", vec!["\\", "{{", " x }}"]),
+            ("\\\\\\\\{{ x }}", vec!["\\\\", "{{ x }}"]),
+            ("a\\\\\\\\\\{{ x }}", vec!["a\\\\", "{{", " x }}"]),
+            ("a\\b \\\\ {{ x }}", vec!["a\\b \\\\ ", "{{ x }}"]),
+        ];
+
+        for (source, expected) in cases.iter() {
+            let template = Template::from(source.to_string());
+            let values: Vec<&str> = template.into_iter()
+                .map(|slice| match slice {
+                    TemplateSlice::Text { value, .. } | TemplateSlice::Code { value, .. } => value,
+                    other => panic!("Unexpected slice {:?}", other),
+                })
+                .collect();
+            assert_eq!(&values, expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn should_skip_string_literals_in_code() {
+        let template = Template::from("{{ concat \"}}\" \"\\\"}}\" }}!".to_string());
+        let slices: Vec<TemplateSlice> = template.into_iter().collect();
+        let expected = vec![
+            TemplateSlice::Code {
+                value: "{{ concat \"}}\" \"\\\"}}\" }}",
+                start_position: 0,
+                end_position: 24,
+            },
+            TemplateSlice::Text {
+                value: "!",
+                start_position: 24,
+                end_position: 25,
+            },
+        ];
+        assert_eq!(slices, expected);
+    }
+
+    #[test]
+    fn should_end_code_with_unterminated_string_at_first_delimiter() {
+        let template = Template::from("{{ concat \"a }} rest \"b\" {{ x }}".to_string());
+        let slices: Vec<TemplateSlice> = template.into_iter().collect();
+        let expected = vec![
+            TemplateSlice::Code {
+                value: "{{ concat \"a }}",
+                start_position: 0,
+                end_position: 15,
+            },
+            TemplateSlice::Text {
+                value: " rest \"b\" ",
+                start_position: 15,
+                end_position: 25,
+            },
+            TemplateSlice::Code {
+                value: "{{ x }}",
+                start_position: 25,
+                end_position: 32,
+            },
+        ];
+        assert_eq!(slices, expected);
+    }
+
+    #[test]
+    fn should_find_comments() {
+        let template = Template::from("a {{# b }}\n \"c #}}{{ d }}{{#}} #}}".to_string());
+        let slices: Vec<TemplateSlice> = template.into_iter().collect();
+        let expected = vec![
+            TemplateSlice::Text {
+                value: "a ",
+                start_position: 0,
+                end_position: 2,
+            },
+            TemplateSlice::Comment {
+                value: "{{# b }}\n \"c #}}",
+                start_position: 2,
+                end_position: 18,
+            },
+            TemplateSlice::Code {
+                value: "{{ d }}",
+                start_position: 18,
+                end_position: 25,
+            },
+            TemplateSlice::Comment {
+                value: "{{#}} #}}",
+                start_position: 25,
+                end_position: 34,
+            },
+        ];
+        assert_eq!(slices, expected);
+    }
+
+    #[test]
+    fn should_return_unterminated_blocks() {
+        let template = Template::from("a {{ b }} {{ \"c }}\" d".to_string());
+        let slices: Vec<TemplateSlice> = template.into_iter().collect();
+        let expected = vec![
+            TemplateSlice::Text {
+                value: "a ",
+                start_position: 0,
+                end_position: 2,
+            },
+            TemplateSlice::Code {
+                value: "{{ b }}",
+                start_position: 2,
+                end_position: 9,
+            },
+            TemplateSlice::Text {
+                value: " ",
+                start_position: 9,
+                end_position: 10,
+            },
+            TemplateSlice::Unterminated {
+                value: "{{ \"c }}\" d",
+                start_position: 10,
+                end_position: 21,
+            },
+        ];
+        assert_eq!(slices, expected);
+
+        let template = Template::from("{{# comment }}".to_string());
+        let slices: Vec<TemplateSlice> = template.into_iter().collect();
+        assert_eq!(slices, vec![TemplateSlice::Unterminated {
+            value: "{{# comment }}",
+            start_position: 0,
+            end_position: 14,
+        }]);
+    }
+}
@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+use criterion::{criterion_group, criterion_main, Criterion, black_box};
+use rubble_templates::compile_template_from_string;
+use rubble_templates::std_fun::std_functions;
+use rubble_templates_core::evaluator::Context;
+use rubble_templates_core::value::Value;
+use rubble_templates_evaluators::simple::compiler::TemplateCompiler;
+use rubble_templates_evaluators::simple::evaluator::SimpleEvaluationEngine;
+use rubble_templates_evaluators::simple::template::Template;
+
+const TEMPLATE: &str = "Hello {{ user.name }}!
+{{ for item in items }}{{ loop.index }}. {{ item.title }} costs {{ + item.price 10 }}
+{{ else }}No items.
+{{ end }}{{ if user.admin }}Admin: {{ user.name }}{{ end }}";
+
+fn variables() -> HashMap<String, Value> {
+    let mut user = HashMap::new();
+    user.insert("name".to_string(), Value::from("Joe"));
+    user.insert("admin".to_string(), Value::from(true));
+
+    let items: Vec<Value> = (0..10).map(|index| {
+        let mut item = HashMap::new();
+        item.insert("title".to_string(), Value::from(format!("Item {}", index)));
+        item.insert("price".to_string(), Value::from(index * 5));
+        Value::from(item)
+    }).collect();
+
+    let mut variables = HashMap::new();
+    variables.insert("user".to_string(), Value::from(user));
+    variables.insert("items".to_string(), Value::from(items));
+    variables
+}
+
+fn rendering(c: &mut Criterion) {
+    let variables = variables();
+
+    c.bench_function("compile_template_from_string", |b| b.iter(|| {
+        compile_template_from_string(black_box(TEMPLATE.to_string()), variables.clone(), std_functions()).unwrap()
+    }));
+
+    let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(std_functions()));
+    let compiled = compiler.precompile(&Template::from(TEMPLATE.to_string())).unwrap();
+    c.bench_function("render_precompiled_template", |b| b.iter(|| {
+        compiler.render(black_box(&compiled), Context::with_variables(variables.clone())).unwrap()
+    }));
+}
+
+criterion_group!(benches, rendering);
+criterion_main!(benches);
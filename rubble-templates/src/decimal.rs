@@ -0,0 +1,440 @@
+//! Exact decimal numbers, eg. for money amounts.
+//!
+//! See [Decimal] for more info.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+/// Describes how to round a [Decimal] that has more decimal places than required.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RoundingMode {
+    /// Rounds to the nearest neighbour, halfway cases are rounded away from zero, eg. `2.5` to `3` and `-2.5` to `-3`.
+    HalfUp,
+    /// Rounds to the nearest neighbour, halfway cases are rounded to the even neighbour, eg. `2.5` to `2` and `3.5` to `4`.
+    HalfEven,
+    /// Discards the extra digits (rounds towards zero), eg. `2.9` to `2` and `-2.9` to `-2`.
+    Truncate,
+}
+
+impl RoundingMode {
+    /// Finds a rounding mode by its name: `half_up`, `half_even` or `truncate`.
+    pub fn from_name(name: &str) -> Option<RoundingMode> {
+        match name {
+            "half_up" => Some(RoundingMode::HalfUp),
+            "half_even" => Some(RoundingMode::HalfEven),
+            "truncate" => Some(RoundingMode::Truncate),
+            _ => None,
+        }
+    }
+}
+
+/// An arbitrary-precision decimal number.
+///
+/// A decimal is stored as digits of an integer and a scale (the number of digits after the decimal point),
+/// so `12.30` is `1230` with scale `2`. Floating point numbers are never used, so adding, subtracting and multiplying is exact.
+/// Division and [rounding](Decimal::round) use an explicit scale and a [RoundingMode].
+///
+/// The scale is kept in results, eg. `1.10 + 2.205` is `3.305` and `0.10 * 3` is `0.30`.
+/// Decimals are equal only if they have the same value and the same scale.
+///
+/// ```
+/// use rubble_templates::decimal::{Decimal, RoundingMode};
+///
+/// let price: Decimal = "0.10".parse().unwrap();
+/// let total = &(&price + &"0.20".parse().unwrap()) * &Decimal::from(3);
+/// assert_eq!(total.to_string(), "0.90");
+///
+/// let tax = total.divide(&Decimal::from(7), 2, RoundingMode::HalfUp).unwrap();
+/// assert_eq!(tax.to_string(), "0.13");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Decimal {
+    negative: bool,
+    /// Digits of the unscaled value, the least significant first, without leading zeros (zero has no digits).
+    digits: Vec<u8>,
+    scale: u32,
+}
+
+impl Decimal {
+    fn new(negative: bool, mut digits: Vec<u8>, scale: u32) -> Decimal {
+        trim(&mut digits);
+        Decimal {
+            negative: negative && !digits.is_empty(),
+            digits,
+            scale,
+        }
+    }
+
+    /// Returns the number of digits after the decimal point.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// Rounds the decimal to the given number of digits after the decimal point.
+    /// If the decimal has fewer digits, they are padded with zeros.
+    ///
+    /// ```
+    /// use rubble_templates::decimal::{Decimal, RoundingMode};
+    ///
+    /// let value: Decimal = "2.345".parse().unwrap();
+    /// assert_eq!(value.round(2, RoundingMode::HalfUp).to_string(), "2.35");
+    /// assert_eq!(value.round(2, RoundingMode::HalfEven).to_string(), "2.34");
+    /// assert_eq!(value.round(2, RoundingMode::Truncate).to_string(), "2.34");
+    /// assert_eq!(value.round(5, RoundingMode::Truncate).to_string(), "2.34500");
+    /// ```
+    pub fn round(&self, scale: u32, mode: RoundingMode) -> Decimal {
+        if scale >= self.scale {
+            return Decimal::new(self.negative, self.digits_with_scale(scale), scale);
+        }
+
+        let divisor = shift(&[1], self.scale - scale);
+        let (quotient, remainder) = divide_digits(&self.digits, &divisor);
+        Decimal::new(self.negative, round_quotient(quotient, &remainder, &divisor, mode), scale)
+    }
+
+    /// Divides the decimal, the result has the given number of digits after the decimal point.
+    ///
+    /// Returns `None` when dividing by zero.
+    ///
+    /// ```
+    /// use rubble_templates::decimal::{Decimal, RoundingMode};
+    ///
+    /// let value = Decimal::from(10);
+    /// assert_eq!(value.divide(&Decimal::from(3), 4, RoundingMode::HalfUp).unwrap().to_string(), "3.3333");
+    /// assert_eq!(value.divide(&Decimal::from(-4), 0, RoundingMode::HalfEven).unwrap().to_string(), "-2");
+    /// assert_eq!(value.divide(&Decimal::from(0), 2, RoundingMode::HalfUp), None);
+    /// ```
+    pub fn divide(&self, divisor: &Decimal, scale: u32, mode: RoundingMode) -> Option<Decimal> {
+        if divisor.is_zero() {
+            return None;
+        }
+
+        // self / divisor = (digits / divisor digits) * 10^(divisor scale - self scale), scaled up by 10^scale
+        let exponent = i64::from(scale) + i64::from(divisor.scale) - i64::from(self.scale);
+        let numerator = shift(&self.digits, u32::try_from(exponent.max(0)).ok()?);
+        let denominator = shift(&divisor.digits, u32::try_from((-exponent).max(0)).ok()?);
+        let (quotient, remainder) = divide_digits(&numerator, &denominator);
+
+        Some(Decimal::new(
+            self.negative != divisor.negative,
+            round_quotient(quotient, &remainder, &denominator, mode),
+            scale,
+        ))
+    }
+
+    /// Returns the digits of the unscaled value for a scale that is not less than the current one.
+    fn digits_with_scale(&self, scale: u32) -> Vec<u8> {
+        shift(&self.digits, scale - self.scale)
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        let mut magnitude = value.unsigned_abs();
+        let mut digits = vec![];
+        while magnitude > 0 {
+            digits.push((magnitude % 10) as u8);
+            magnitude /= 10;
+        }
+        Decimal::new(value < 0, digits, 0)
+    }
+}
+
+/// An error returned when a text is not a decimal number.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseDecimalError {
+    pub text: String,
+}
+
+impl Display for ParseDecimalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` is not a decimal number", self.text)
+    }
+}
+
+impl Error for ParseDecimalError {}
+
+/// Parses a decimal, eg. `12`, `-0.50` or `+.5`. The scale is the number of digits after the decimal point.
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || ParseDecimalError { text: text.to_string() };
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (integer, fraction) = match unsigned.find('.') {
+            Some(index) => (&unsigned[..index], &unsigned[(index + 1)..]),
+            None => (unsigned, ""),
+        };
+
+        let valid = !(integer.is_empty() && fraction.is_empty())
+            && integer.chars().chain(fraction.chars()).all(|it| it.is_ascii_digit());
+        if !valid {
+            return Err(error());
+        }
+
+        let digits = integer.bytes().chain(fraction.bytes())
+            .rev()
+            .map(|digit| digit - b'0')
+            .collect();
+        let scale = u32::try_from(fraction.len()).map_err(|_| error())?;
+        Ok(Decimal::new(negative, digits, scale))
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let scale = self.scale as usize;
+        let mut text: String = self.digits.iter().rev().map(|digit| char::from(b'0' + digit)).collect();
+        if text.len() <= scale {
+            text = "0".repeat(scale + 1 - text.len()) + &text;
+        }
+        if scale > 0 {
+            text.insert(text.len() - scale, '.');
+        }
+        if self.negative {
+            text.insert(0, '-');
+        }
+        write!(f, "{}", text)
+    }
+}
+
+impl Neg for &Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal::new(!self.negative, self.digits.clone(), self.scale)
+    }
+}
+
+impl Add for &Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: &Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        let a = self.digits_with_scale(scale);
+        let b = other.digits_with_scale(scale);
+
+        if self.negative == other.negative {
+            return Decimal::new(self.negative, add_digits(&a, &b), scale);
+        }
+        match compare_digits(&a, &b) {
+            Ordering::Less => Decimal::new(other.negative, subtract_digits(&b, &a), scale),
+            _ => Decimal::new(self.negative, subtract_digits(&a, &b), scale),
+        }
+    }
+}
+
+impl Sub for &Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: &Decimal) -> Decimal {
+        self + &(-other)
+    }
+}
+
+impl Mul for &Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: &Decimal) -> Decimal {
+        Decimal::new(
+            self.negative != other.negative,
+            multiply_digits(&self.digits, &other.digits),
+            self.scale + other.scale,
+        )
+    }
+}
+
+/// Rounds the result of a division with the remainder.
+fn round_quotient(quotient: Vec<u8>, remainder: &[u8], divisor: &[u8], mode: RoundingMode) -> Vec<u8> {
+    if remainder.is_empty() || mode == RoundingMode::Truncate {
+        return quotient;
+    }
+
+    let round_up = match compare_digits(&add_digits(remainder, remainder), divisor) {
+        Ordering::Greater => true,
+        Ordering::Equal => mode == RoundingMode::HalfUp || quotient.first().is_some_and(|digit| digit % 2 == 1),
+        Ordering::Less => false,
+    };
+    if round_up {
+        add_digits(&quotient, &[1])
+    } else {
+        quotient
+    }
+}
+
+fn trim(digits: &mut Vec<u8>) {
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+}
+
+/// Multiplies digits by `10^places`.
+fn shift(digits: &[u8], places: u32) -> Vec<u8> {
+    if digits.is_empty() {
+        return vec![];
+    }
+    let mut shifted = vec![0; places as usize];
+    shifted.extend_from_slice(digits);
+    shifted
+}
+
+fn compare_digits(a: &[u8], b: &[u8]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for index in 0..a.len().max(b.len()) {
+        let sum = a.get(index).unwrap_or(&0) + b.get(index).unwrap_or(&0) + carry;
+        result.push(sum % 10);
+        carry = sum / 10;
+    }
+    if carry > 0 {
+        result.push(carry);
+    }
+    result
+}
+
+/// Subtracts digits, `a` cannot be less than `b`.
+fn subtract_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (index, digit) in a.iter().enumerate() {
+        let subtrahend = b.get(index).unwrap_or(&0) + borrow;
+        if *digit >= subtrahend {
+            result.push(digit - subtrahend);
+            borrow = 0;
+        } else {
+            result.push(digit + 10 - subtrahend);
+            borrow = 1;
+        }
+    }
+    trim(&mut result);
+    result
+}
+
+fn multiply_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut products = vec![0_u64; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            products[i + j] += u64::from(*x) * u64::from(*y);
+        }
+    }
+
+    let mut result = Vec::with_capacity(products.len());
+    let mut carry = 0;
+    for product in products {
+        let sum = product + carry;
+        result.push((sum % 10) as u8);
+        carry = sum / 10;
+    }
+    trim(&mut result);
+    result
+}
+
+/// Divides digits using long division, returns the quotient and the remainder.
+fn divide_digits(a: &[u8], b: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut quotient = vec![0; a.len()];
+    let mut remainder: Vec<u8> = vec![];
+
+    for index in (0..a.len()).rev() {
+        remainder.insert(0, a[index]);
+        trim(&mut remainder);
+
+        let mut digit = 0;
+        while compare_digits(&remainder, b) != Ordering::Less {
+            remainder = subtract_digits(&remainder, b);
+            digit += 1;
+        }
+        quotient[index] = digit;
+    }
+
+    trim(&mut quotient);
+    (quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decimal::{Decimal, RoundingMode};
+
+    fn decimal(text: &str) -> Decimal {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn should_parse_and_display_decimals() {
+        let cases = [
+            ("0", "0"),
+            ("-0.00", "0.00"),
+            ("007.50", "7.50"),
+            ("+.5", "0.5"),
+            ("-12.", "-12"),
+            ("123456789012345678901234567890.123", "123456789012345678901234567890.123"),
+        ];
+
+        for (input, expected) in cases.iter() {
+            assert_eq!(decimal(input).to_string(), *expected, "{}", input);
+        }
+
+        for input in &["", ".", "-", "1.2.3", "1e5", "abc", " 1"] {
+            assert!(input.parse::<Decimal>().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn should_calculate_exactly() {
+        assert_eq!((&decimal("0.1") + &decimal("0.2")).to_string(), "0.3");
+        assert_eq!((&decimal("1.10") + &decimal("-2.205")).to_string(), "-1.105");
+        assert_eq!((&decimal("-0.5") - &decimal("-0.5")).to_string(), "0.0");
+        assert_eq!((&decimal("0.10") * &decimal("-3")).to_string(), "-0.30");
+        assert_eq!(
+            (&decimal("99999999999999999999.99") * &decimal("99999999999999999999.99")).to_string(),
+            "9999999999999999999998000000000000000000.0001"
+        );
+        assert_eq!((&Decimal::from(i64::MIN) - &Decimal::from(1)).to_string(), "-9223372036854775809");
+    }
+
+    #[test]
+    fn should_round_with_mode() {
+        let cases = [
+            ("2.5", RoundingMode::HalfUp, "3"),
+            ("-2.5", RoundingMode::HalfUp, "-3"),
+            ("2.5", RoundingMode::HalfEven, "2"),
+            ("3.5", RoundingMode::HalfEven, "4"),
+            ("-3.5", RoundingMode::HalfEven, "-4"),
+            ("2.51", RoundingMode::HalfEven, "3"),
+            ("-2.9", RoundingMode::Truncate, "-2"),
+            ("0.4", RoundingMode::HalfUp, "0"),
+            ("9.99", RoundingMode::HalfUp, "10"),
+        ];
+
+        for (input, mode, expected) in cases.iter() {
+            assert_eq!(decimal(input).round(0, *mode).to_string(), *expected, "{} {:?}", input, mode);
+        }
+    }
+
+    #[test]
+    fn should_divide_with_scale() {
+        let divide = |a: &str, b: &str, scale: u32, mode: RoundingMode| decimal(a).divide(&decimal(b), scale, mode).map(|it| it.to_string());
+
+        assert_eq!(divide("1", "3", 5, RoundingMode::HalfUp), Some("0.33333".to_string()));
+        assert_eq!(divide("2", "3", 2, RoundingMode::HalfUp), Some("0.67".to_string()));
+        assert_eq!(divide("2", "3", 2, RoundingMode::Truncate), Some("0.66".to_string()));
+        assert_eq!(divide("0.125", "0.5", 1, RoundingMode::HalfEven), Some("0.2".to_string()));
+        assert_eq!(divide("-0.125", "0.5", 1, RoundingMode::HalfUp), Some("-0.3".to_string()));
+        assert_eq!(divide("1234.5", "0.001", 0, RoundingMode::HalfUp), Some("1234500".to_string()));
+        assert_eq!(divide("1", "0.00", 2, RoundingMode::HalfUp), None);
+    }
+}
@@ -5,6 +5,7 @@
 //! ### Syntax
 //!
 //! By default, rubble-templates while parsing a template looks for all blocks starting `{{` and ending with `}}` those are marked as an evaluation spots with code that can be evaluated by an `Evaluator`.
+//! The delimiters can be changed with [`Syntax`](rubble_templates_evaluators::simple::template::Syntax).
 //!
 //! Given the following example:
 //! ```text
@@ -84,7 +85,7 @@
 //! // compile_template_from_string parameters
 //! let raw_input = "2 + 3 = {{ + 2 3 }}".to_string();
 //! let functions = std_functions();
-//! let variables = HashMap::new();
+//! let variables: HashMap<String, String> = HashMap::new();
 //!
 //! // compilation process
 //! let template = Template::from(raw_input);
@@ -96,16 +97,41 @@
 //!
 //! assert_eq!(result.unwrap(), "2 + 3 = 5".to_string());
 //! ```
+//!
+//! ### Rendering many times
+//!
+//! Functions like [`compile_template_from_string`] parse the template with each invocation.
+//! When the same template is rendered repeatedly, parse it once with [`TemplateCompiler::precompile`]
+//! and render the resulting [`CompiledTemplate`](rubble_templates_evaluators::simple::compiler::CompiledTemplate) with new contexts:
+//!
+//! ```rust
+//! use rubble_templates::std_fun::std_functions;
+//! use rubble_templates_evaluators::simple::template::Template;
+//! use rubble_templates_evaluators::simple::evaluator::SimpleEvaluationEngine;
+//! use rubble_templates_evaluators::simple::compiler::TemplateCompiler;
+//! use rubble_templates_core::evaluator::Context;
+//!
+//! let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(std_functions()));
+//! let compiled = compiler.precompile(&Template::from("{{ + x 1 }}".to_string())).unwrap();
+//!
+//! for x in 0..3 {
+//!     let mut context = Context::empty();
+//!     context.set_variable("x", x);
+//!     assert_eq!(compiler.render(&compiled, context).unwrap(), (x + 1).to_string());
+//! }
+//! ```
 
 use std::path::PathBuf;
 use std::collections::HashMap;
 use rubble_templates_core::evaluator::{Function, Context};
 use std::error::Error;
 use rubble_templates_core::compiler::{CompilationError, Compiler};
+use rubble_templates_core::value::Value;
 use rubble_templates_evaluators::simple::template::Template;
 use rubble_templates_evaluators::simple::evaluator::SimpleEvaluationEngine;
 use rubble_templates_evaluators::simple::compiler::TemplateCompiler;
 
+pub mod decimal;
 pub mod std_fun;
 
 /// Compiles template from file.
@@ -118,11 +144,18 @@ pub mod std_fun;
 ///
 /// Template can look like the following: `Some template {{ variable }} - or something`.
 /// Code that will be evaluated should be put between `{{` and `}}`.
-pub fn compile_template_from_file(file: PathBuf, variables: HashMap<String, String>, functions: HashMap<String, Box<dyn Function>>) -> Result<String, Box<dyn Error>> {
+///
+/// Variables can be any type convertible to [Value], eg. `String`, `i64` or `Vec<String>`.
+///
+/// Compilation errors are returned as [TemplateError](rubble_templates_core::compiler::TemplateError)s,
+/// which describe the file, line and column of the error.
+pub fn compile_template_from_file<V: Into<Value>>(file: PathBuf, variables: HashMap<String, V>, functions: HashMap<String, Box<dyn Function>>) -> Result<String, Box<dyn Error>> {
     let template = Template::read_from(&file)?;
+    let engine = SimpleEvaluationEngine::from(functions);
+    let compiler = TemplateCompiler::new(engine);
 
-    compile_template_from(template, variables, functions)
-        .map_err(|error| Box::new(error) as Box<dyn Error>)
+    compiler.compile(&template, Context::with_variables(variables))
+        .map_err(|error| Box::new(template.locate(error)) as Box<dyn Error>)
 }
 
 /// Compiles template from String.
@@ -134,7 +167,7 @@ pub fn compile_template_from_file(file: PathBuf, variables: HashMap<String, Stri
 ///
 /// Template can look like the following: `Some template {{ variable }} - or something`.
 /// Code that will be evaluated should be put between `{{` and `}}`.
-pub fn compile_template_from_string(template: String, variables: HashMap<String, String>, functions: HashMap<String, Box<dyn Function>>) -> Result<String, CompilationError> {
+pub fn compile_template_from_string<V: Into<Value>>(template: String, variables: HashMap<String, V>, functions: HashMap<String, Box<dyn Function>>) -> Result<String, CompilationError> {
     compile_template_from(Template::from(template), variables, functions)
 }
 
@@ -145,13 +178,60 @@ pub fn compile_template_from_string(template: String, variables: HashMap<String,
 ///
 /// Template can look like the following: `Some template {{ variable }} - or something`.
 /// Code that will be evaluated should be put between `{{` and `}}`.
-pub fn compile_template_from(template: Template, variables: HashMap<String, String>, functions: HashMap<String, Box<dyn Function>>) -> Result<String, CompilationError> {
+pub fn compile_template_from<V: Into<Value>>(template: Template, variables: HashMap<String, V>, functions: HashMap<String, Box<dyn Function>>) -> Result<String, CompilationError> {
     let engine = SimpleEvaluationEngine::from(functions);
     let compiler = TemplateCompiler::new(engine);
 
     compiler.compile(&template, Context::with_variables(variables))
 }
 
+/// Compiles template from String, using any [Serialize](serde::Serialize) implementation as variables.
+///
+/// Available with the `serde` feature. The data has to be serialized as a map (eg. a struct or a `HashMap`),
+/// its fields become variables (see [Context::from_serialize]).
+/// Nested fields can be accessed with paths, eg. `{{ user.address.city }}`.
+///
+/// Example:
+/// ```
+/// use serde::Serialize;
+/// use rubble_templates::std_fun::std_functions;
+/// use rubble_templates::compile_template_from_serialize;
+///
+/// #[derive(Serialize)]
+/// struct Invoice {
+///     number: u32,
+///     client: String,
+/// }
+///
+/// let invoice = Invoice { number: 17, client: "ACME".to_string() };
+/// let result = compile_template_from_serialize("Invoice {{ number }} for {{ client }}".to_string(), &invoice, std_functions());
+///
+/// assert_eq!(result.ok(), Some("Invoice 17 for ACME".to_string()));
+/// ```
+#[cfg(feature = "serde")]
+pub fn compile_template_from_serialize<T: serde::Serialize + ?Sized>(template: String, data: &T, functions: HashMap<String, Box<dyn Function>>) -> Result<String, Box<dyn Error>> {
+    compile_serialized(Template::from(template), data, functions)
+}
+
+/// Compiles template from file, using any [Serialize](serde::Serialize) implementation as variables.
+///
+/// Available with the `serde` feature. Works like [compile_template_from_serialize], but reads the template from a file,
+/// so errors describe the file, line and column like in [compile_template_from_file].
+#[cfg(feature = "serde")]
+pub fn compile_template_from_file_serialize<T: serde::Serialize + ?Sized>(file: PathBuf, data: &T, functions: HashMap<String, Box<dyn Function>>) -> Result<String, Box<dyn Error>> {
+    compile_serialized(Template::read_from(&file)?, data, functions)
+}
+
+#[cfg(feature = "serde")]
+fn compile_serialized<T: serde::Serialize + ?Sized>(template: Template, data: &T, functions: HashMap<String, Box<dyn Function>>) -> Result<String, Box<dyn Error>> {
+    let context = Context::from_serialize(data)?;
+    let engine = SimpleEvaluationEngine::from(functions);
+    let compiler = TemplateCompiler::new(engine);
+
+    compiler.compile(&template, context)
+        .map_err(|error| Box::new(template.locate(error)) as Box<dyn Error>)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::compile_template_from_file;
@@ -159,6 +239,7 @@ mod tests {
     use std::collections::HashMap;
     use rubble_templates_core::evaluator::Function;
     use rubble_templates_core::functions::SimpleFunction;
+    use rubble_templates_core::value::Value;
 
     #[test]
     fn should_compile_template() {
@@ -174,13 +255,86 @@ mod tests {
         assert_eq!(result.ok(), Some("Some template. Hello world!.\n\nThis shows a function evaluation usage example:\n2 + 2 = 4".to_string()));
     }
 
-    fn plus_function(parameters: &[String]) -> String {
-        parameters.iter()
+    #[test]
+    fn should_describe_error_location() {
+        let file = PathBuf::from("test-assets/complex-template");
+        let mut variables: HashMap<String, String> = HashMap::new();
+        variables.insert("hello".to_string(), "Hello world!".to_string());
+
+        let result = compile_template_from_file(file, variables, HashMap::new());
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "unknown symbol `plus`\n --> test-assets/complex-template:4:12\n  |\n4 | 2 + 2 = {{ plus 2 2 }}\n  |            ^"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn should_compile_template_from_serialize() {
+        use serde::Serialize;
+        use crate::compile_template_from_serialize;
+
+        #[derive(Serialize)]
+        enum Status {
+            Active,
+            Suspended { reason: String },
+        }
+
+        #[derive(Serialize)]
+        struct Account {
+            name: String,
+            status: Status,
+            previous: Vec<Status>,
+        }
+
+        let account = Account {
+            name: "Joe".to_string(),
+            status: Status::Active,
+            previous: vec![Status::Suspended { reason: "unpaid".to_string() }],
+        };
+        let template = "{{ name }} is {{ status }}, was suspended for: {{ previous.0.Suspended.reason }}".to_string();
+
+        let result = compile_template_from_serialize(template, &account, HashMap::new());
+        assert_eq!(result.ok(), Some("Joe is Active, was suspended for: unpaid".to_string()));
+
+        let result = compile_template_from_serialize("{{ 0 }}".to_string(), &vec![1, 2], HashMap::new());
+        assert!(result.is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn should_compile_template_file_from_serialize() {
+        use serde::Serialize;
+        use crate::compile_template_from_file_serialize;
+
+        #[derive(Serialize)]
+        struct Greeting {
+            hello: &'static str,
+        }
+
+        let mut functions: HashMap<String, Box<dyn Function>> = HashMap::new();
+        functions.insert("plus".to_string(), SimpleFunction::new(plus_function));
+        let file = PathBuf::from("test-assets/complex-template");
+
+        let result = compile_template_from_file_serialize(file.clone(), &Greeting { hello: "Hello world!" }, functions);
+        assert_eq!(result.ok(), Some("Some template. Hello world!.\n\nThis shows a function evaluation usage example:\n2 + 2 = 4".to_string()));
+
+        let result = compile_template_from_file_serialize(file, &Greeting { hello: "Hello world!" }, HashMap::new());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "unknown symbol `plus`\n --> test-assets/complex-template:4:12\n  |\n4 | 2 + 2 = {{ plus 2 2 }}\n  |            ^"
+        );
+    }
+
+    fn plus_function(parameters: &[Value]) -> Value {
+        Value::Integer(
+            parameters.iter()
                 .map(|param|
-                    param.parse::<i32>().unwrap()
+                    param.as_i64().unwrap()
                 )
-                .sum::<i32>()
-                .to_string()
+                .sum::<i64>()
+        )
     }
 }
 
@@ -27,11 +27,17 @@
 //! Example usages are in the [test-assets](https://github.com/multicatch/rubble-rs/tree/master/rubble-templates/test-assets).
 //! See the "stdlib-template" and "stdlib-template-output" to see standard functions in action.
 
+pub mod case;
+pub mod decimal;
+pub mod logic;
 pub mod math;
 pub mod strings;
 
 use rubble_templates_core::evaluator::Function;
 use std::collections::HashMap;
+use crate::std_fun::case::case_functions;
+use crate::std_fun::decimal::decimal_functions;
+use crate::std_fun::logic::logic_functions;
 use crate::std_fun::math::math_functions;
 use crate::std_fun::strings::string_functions;
 
@@ -40,11 +46,17 @@ use crate::std_fun::strings::string_functions;
 /// This is a cumulative set of the following functions:
 /// * [`math_functions`](math_functions) - All math-related functions.
 /// * [`string_functions`](string_functions) - All math-related functions.
+/// * [`logic_functions`](logic_functions) - All comparison and boolean functions.
+/// * [`case_functions`](case_functions) - All case conversion functions.
+/// * [`decimal_functions`](decimal_functions) - All exact decimal arithmetic functions.
 ///
 pub fn std_functions() -> HashMap<String, Box<dyn Function>> {
     let mut functions: HashMap<String, Box<dyn Function>> = HashMap::new();
     functions.extend(math_functions());
     functions.extend(string_functions());
+    functions.extend(logic_functions());
+    functions.extend(case_functions());
+    functions.extend(decimal_functions());
     functions
 }
 
@@ -67,5 +79,38 @@ mod tests {
 
         assert_eq!(result.ok(), Some(expected));
     }
-}
 
+    #[test]
+    fn should_not_escape_safe_values() {
+        use rubble_templates_core::compiler::Compiler;
+        use rubble_templates_core::evaluator::Context;
+        use rubble_templates_evaluators::simple::compiler::TemplateCompiler;
+        use rubble_templates_evaluators::simple::escape::Escaping;
+        use rubble_templates_evaluators::simple::evaluator::SimpleEvaluationEngine;
+        use rubble_templates_evaluators::simple::template::Template;
+
+        let template = Template::from("{{ link }} {{ safe link }} {{ raw \"<b>\" name \"</b>\" }}".to_string());
+        let compiler = TemplateCompiler::new(SimpleEvaluationEngine::from(std_functions()))
+            .with_escaping(Escaping::Html);
+        let mut context = Context::empty();
+        context.set_variable("link", "<a href=\"/\">home</a>");
+        context.set_variable("name", "Joe");
+
+        let result = compiler.compile(&template, context);
+
+        assert_eq!(result, Ok("&lt;a href=&quot;/&quot;&gt;home&lt;/a&gt; <a href=\"/\">home</a> <b>Joe</b>".to_string()));
+    }
+
+    #[test]
+    fn should_use_functions_as_filters() {
+        use crate::compile_template_from_string;
+
+        let mut variables: HashMap<String, String> = HashMap::new();
+        variables.insert("name".to_string(), "  Joe ".to_string());
+        let template = "{{ name | trim | concat \"!\" }} {{ if name | trim }}{{ + 1 2 | + 3 }}{{ end }}".to_string();
+
+        let result = compile_template_from_string(template, variables, std_functions());
+
+        assert_eq!(result.ok(), Some("Joe! 6".to_string()));
+    }
+}
@@ -0,0 +1,234 @@
+//! Provides case conversion functions, eg. for generating code from schema names.
+//!
+//! See [`std_fun`](rubble-templates::std_fun) or [`case_functions`] for more info.
+//!
+use std::collections::HashMap;
+use rubble_templates_core::evaluator::Function;
+use rubble_templates_core::functions::SimpleFunction;
+use rubble_templates_core::value::Value;
+use crate::std_fun::strings::concat_function;
+
+/// Provides a set of case conversion functions.
+///
+/// Available functions:
+/// * [`snake_case`](snake_case_function) - Converts the text to `snake_case`.
+/// * [`camel_case`](camel_case_function) - Converts the text to `camelCase`.
+/// * [`pascal_case`](pascal_case_function) - Converts the text to `PascalCase`.
+/// * [`kebab_case`](kebab_case_function) - Converts the text to `kebab-case`.
+/// * [`screaming_snake_case`](screaming_snake_case_function) - Converts the text to `SCREAMING_SNAKE_CASE`.
+/// * [`title_case`](title_case_function) - Converts the text to `Title Case`.
+///
+/// The text is split into words as described in [split_words], so any case can be converted to any other case.
+/// Digits never start a word, they belong to the preceding word, eg. `UserProfile2FA` is `user_profile2_fa`
+/// in snake case and `v2API` is `v2_api`. Only an upper case letter after a digit starts a new word.
+/// If there is more than one parameter, then the parameters are concatenated first.
+pub fn case_functions() -> HashMap<String, Box<dyn Function>> {
+    let mut functions: HashMap<String, Box<dyn Function>> = HashMap::new();
+    functions.insert("snake_case".to_string(), SimpleFunction::new(snake_case_function));
+    functions.insert("camel_case".to_string(), SimpleFunction::new(camel_case_function));
+    functions.insert("pascal_case".to_string(), SimpleFunction::new(pascal_case_function));
+    functions.insert("kebab_case".to_string(), SimpleFunction::new(kebab_case_function));
+    functions.insert("screaming_snake_case".to_string(), SimpleFunction::new(screaming_snake_case_function));
+    functions.insert("title_case".to_string(), SimpleFunction::new(title_case_function));
+    functions
+}
+
+/// Converts the text to `snake_case`.
+///
+/// Eg.
+/// ```text
+/// snake_case "HTTPServerError"
+/// snake_case "user-id v2"
+/// ```
+/// Expected output:
+/// ```text
+/// http_server_error
+/// user_id_v2
+/// ```
+pub fn snake_case_function(parameters: &[Value]) -> Value {
+    convert(parameters, "_", |_, word| word.to_lowercase())
+}
+
+/// Converts the text to `camelCase`. Acronyms are capitalized like other words.
+///
+/// Eg.
+/// ```text
+/// camel_case "user_id"
+/// camel_case "get HTTP response"
+/// ```
+/// Expected output:
+/// ```text
+/// userId
+/// getHttpResponse
+/// ```
+pub fn camel_case_function(parameters: &[Value]) -> Value {
+    convert(parameters, "", |index, word| if index == 0 {
+        word.to_lowercase()
+    } else {
+        capitalize(word)
+    })
+}
+
+/// Converts the text to `PascalCase`. Acronyms are capitalized like other words.
+///
+/// Eg.
+/// ```text
+/// pascal_case "user_id"
+/// pascal_case "parseJSONValue"
+/// ```
+/// Expected output:
+/// ```text
+/// UserId
+/// ParseJsonValue
+/// ```
+pub fn pascal_case_function(parameters: &[Value]) -> Value {
+    convert(parameters, "", |_, word| capitalize(word))
+}
+
+/// Converts the text to `kebab-case`.
+///
+/// Eg.
+/// ```text
+/// kebab_case "UserProfile2FA"
+/// ```
+/// Expected output:
+/// ```text
+/// user-profile2-fa
+/// ```
+pub fn kebab_case_function(parameters: &[Value]) -> Value {
+    convert(parameters, "-", |_, word| word.to_lowercase())
+}
+
+/// Converts the text to `SCREAMING_SNAKE_CASE`.
+///
+/// Eg.
+/// ```text
+/// screaming_snake_case "maxRetryCount"
+/// ```
+/// Expected output:
+/// ```text
+/// MAX_RETRY_COUNT
+/// ```
+pub fn screaming_snake_case_function(parameters: &[Value]) -> Value {
+    convert(parameters, "_", |_, word| word.to_uppercase())
+}
+
+/// Converts the text to `Title Case`, words are separated with spaces.
+///
+/// Eg.
+/// ```text
+/// title_case "created_at_utc"
+/// ```
+/// Expected output:
+/// ```text
+/// Created At Utc
+/// ```
+pub fn title_case_function(parameters: &[Value]) -> Value {
+    convert(parameters, " ", |_, word| capitalize(word))
+}
+
+/// Splits the text into words.
+///
+/// Words are separated by:
+/// * characters that are not letters or digits, eg. ` `, `_`, `-` or `.`,
+/// * a change from a lower case letter or a digit to an upper case letter, eg. `userId` or `utf8Encoder`,
+/// * the end of an acronym, that is an upper case letter followed by a lower case letter after other upper case letters, eg. `HTTPServer`.
+///
+/// Digits belong to the preceding word, eg. `user2` and `base64encode` are single words.
+///
+/// ```
+/// use rubble_templates::std_fun::case::split_words;
+///
+/// assert_eq!(split_words("getHTTPResponse"), vec!["get", "HTTP", "Response"]);
+/// assert_eq!(split_words("__user-id.v2"), vec!["user", "id", "v2"]);
+/// assert_eq!(split_words("utf8Encoder IOError"), vec!["utf8", "Encoder", "IO", "Error"]);
+/// assert_eq!(split_words("HTTP2Server"), vec!["HTTP2", "Server"]);
+/// assert_eq!(split_words("zażółćGęśląJaźń"), vec!["zażółć", "Gęślą", "Jaźń"]);
+/// ```
+pub fn split_words(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut words = vec![];
+    let mut word = String::new();
+
+    for (index, &char) in chars.iter().enumerate() {
+        if !char.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        if let Some(previous) = word.chars().last() {
+            let next = chars.get(index + 1);
+            let starts_word = char.is_uppercase() && (
+                previous.is_lowercase()
+                    || previous.is_numeric()
+                    || (previous.is_uppercase() && next.is_some_and(|next| next.is_lowercase()))
+            );
+            if starts_word {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.push(char);
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn convert<F>(parameters: &[Value], separator: &str, convert_word: F) -> Value
+    where F: Fn(usize, &str) -> String {
+    let text = concat_function(parameters).to_string();
+    let words: Vec<String> = split_words(&text).iter()
+        .enumerate()
+        .map(|(index, word)| convert_word(index, word))
+        .collect();
+    Value::String(words.join(separator))
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rubble_templates_core::value::Value;
+    use crate::std_fun::case::{snake_case_function, split_words};
+
+    #[test]
+    fn should_keep_digits_in_preceding_word() {
+        let cases = [
+            ("UserProfile2FA", vec!["User", "Profile2", "FA"]),
+            ("v2API", vec!["v2", "API"]),
+            ("OAuth2Token", vec!["O", "Auth2", "Token"]),
+            ("base64encode", vec!["base64encode"]),
+            ("ipv4_address", vec!["ipv4", "address"]),
+            ("2fa_code", vec!["2fa", "code"]),
+            ("Level10Boss", vec!["Level10", "Boss"]),
+        ];
+
+        for (text, expected) in cases.iter() {
+            assert_eq!(&split_words(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn should_convert_words_with_digits() {
+        let cases = [
+            ("UserProfile2FA", "user_profile2_fa"),
+            ("v2API", "v2_api"),
+            ("OAuth2Token", "o_auth2_token"),
+            ("HTTP2Server", "http2_server"),
+        ];
+
+        for (text, expected) in cases.iter() {
+            assert_eq!(snake_case_function(&[Value::String(text.to_string())]), Value::String(expected.to_string()), "{}", text);
+        }
+    }
+}
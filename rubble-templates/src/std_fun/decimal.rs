@@ -0,0 +1,345 @@
+//! Provides exact decimal arithmetic functions, eg. for money amounts.
+//!
+//! See [`std_fun`](rubble-templates::std_fun) or [`decimal_functions`] for more info.
+//!
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use rubble_templates_core::evaluator::{Function, Context, SyntaxError, EvaluationError};
+use rubble_templates_core::functions::FunctionWithContext;
+use rubble_templates_core::value::Value;
+use crate::decimal::{Decimal, ParseDecimalError, RoundingMode};
+use crate::std_fun::strings::invalid_parameters;
+
+/// The largest scale (the number of digits after the decimal point) that can be used to round a result.
+///
+/// Rounding pads results with zeros up to the scale, so a larger scale is rejected with an error
+/// instead of allocating memory for arbitrarily many digits.
+pub const MAX_SCALE: u32 = 300;
+
+/// Provides a set of decimal arithmetic functions.
+///
+/// Available functions:
+/// * [`decimal`](decimal_function) - Converts the parameter to a decimal, optionally rounding it.
+/// * [`decimal_add`](decimal_add_function) - Adds decimals.
+/// * [`decimal_sub`](decimal_sub_function) - Subtracts decimals.
+/// * [`decimal_mul`](decimal_mul_function) - Multiplies decimals.
+/// * [`decimal_div`](decimal_div_function) - Divides decimals.
+///
+/// Unlike [math functions](crate::std_fun::math), these functions never use floating point numbers
+/// (see [Decimal]), so eg. `decimal_add "0.1" "0.2"` is exactly `0.3`.
+/// Decimals are passed between functions as text, so a result of one function can be used by another one.
+///
+/// Decimal parameters are integers or text. Literals with a decimal point, like `19.99`, are floats,
+/// so they have to be quoted (`"19.99"`) to keep their exact digits and scale.
+///
+/// The results can be rounded to a scale (the number of digits after the decimal point) with a rounding mode,
+/// given as the last parameters of a function:
+/// * `"half_up"` (default) - halfway cases are rounded away from zero, eg. `2.5` to `3`,
+/// * `"half_even"` - halfway cases are rounded to the even neighbour, eg. `2.5` to `2` and `3.5` to `4`,
+/// * `"truncate"` - the extra digits are discarded, eg. `2.9` to `2`.
+///
+/// The scale cannot be greater than [MAX_SCALE].
+pub fn decimal_functions() -> HashMap<String, Box<dyn Function>> {
+    let mut functions: HashMap<String, Box<dyn Function>> = HashMap::new();
+    functions.insert("decimal".to_string(), FunctionWithContext::new(decimal_function));
+    functions.insert("decimal_add".to_string(), FunctionWithContext::new(decimal_add_function));
+    functions.insert("decimal_sub".to_string(), FunctionWithContext::new(decimal_sub_function));
+    functions.insert("decimal_mul".to_string(), FunctionWithContext::new(decimal_mul_function));
+    functions.insert("decimal_div".to_string(), FunctionWithContext::new(decimal_div_function));
+    functions
+}
+
+/// Converts the parameter to a decimal.
+///
+/// Expects a value, and optionally a scale and a rounding mode. Without a scale, the value is not rounded.
+///
+/// Eg.
+/// ```text
+/// decimal "0012.50"
+/// decimal "2.345" 2
+/// decimal "2.345" 2 "half_even"
+/// decimal 7 2
+/// ```
+/// Expected output:
+/// ```text
+/// 12.50
+/// 2.35
+/// 2.34
+/// 7.00
+/// ```
+pub fn decimal_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    match parameters {
+        [value, rounding @ ..] if rounding.len() <= 2 => rounded(as_decimal(value)?, rounding),
+        _ => Err(invalid_parameters(parameters, "decimal value [scale] [rounding]")),
+    }
+}
+
+/// Adds two decimals.
+///
+/// Expects two values, and optionally a scale and a rounding mode.
+/// Without a scale, the result has as many digits after the decimal point as the more precise value.
+///
+/// Eg.
+/// ```text
+/// decimal_add "0.1" "0.2"
+/// decimal_add "19.99" "5.01"
+/// ```
+/// Expected output:
+/// ```text
+/// 0.3
+/// 25.00
+/// ```
+pub fn decimal_add_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    match parameters {
+        [a, b, rounding @ ..] if rounding.len() <= 2 => rounded(&as_decimal(a)? + &as_decimal(b)?, rounding),
+        _ => Err(invalid_parameters(parameters, "decimal_add a b [scale] [rounding]")),
+    }
+}
+
+/// Subtracts the second decimal from the first one.
+///
+/// Expects two values, and optionally a scale and a rounding mode (see [`decimal_add`](decimal_add_function)).
+///
+/// Eg.
+/// ```text
+/// decimal_sub "0.3" "0.1"
+/// decimal_sub 10 "12.75"
+/// ```
+/// Expected output:
+/// ```text
+/// 0.2
+/// -2.75
+/// ```
+pub fn decimal_sub_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    match parameters {
+        [a, b, rounding @ ..] if rounding.len() <= 2 => rounded(&as_decimal(a)? - &as_decimal(b)?, rounding),
+        _ => Err(invalid_parameters(parameters, "decimal_sub a b [scale] [rounding]")),
+    }
+}
+
+/// Multiplies two decimals.
+///
+/// Expects two values, and optionally a scale and a rounding mode.
+/// Without a scale, the result is exact, so it has as many digits after the decimal point as both values together.
+///
+/// Eg.
+/// ```text
+/// decimal_mul "19.99" 3
+/// decimal_mul "19.99" "0.23"
+/// decimal_mul "19.99" "0.23" 2
+/// ```
+/// Expected output:
+/// ```text
+/// 59.97
+/// 4.5977
+/// 4.60
+/// ```
+pub fn decimal_mul_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    match parameters {
+        [a, b, rounding @ ..] if rounding.len() <= 2 => rounded(&as_decimal(a)? * &as_decimal(b)?, rounding),
+        _ => Err(invalid_parameters(parameters, "decimal_mul a b [scale] [rounding]")),
+    }
+}
+
+/// Divides the first decimal by the second one.
+///
+/// Expects two values and a scale of the result, and optionally a rounding mode.
+/// Division by zero results in an error.
+///
+/// Eg.
+/// ```text
+/// decimal_div 100 3 2
+/// decimal_div "0.125" "0.5" 1 "half_even"
+/// decimal_div 1 0 2
+/// ```
+/// Expected output:
+/// ```text
+/// 33.33
+/// 0.2
+/// (an error: division by zero)
+/// ```
+pub fn decimal_div_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let (a, b, scale, mode) = match parameters {
+        [a, b, scale] => (as_decimal(a)?, as_decimal(b)?, as_scale(scale)?, RoundingMode::HalfUp),
+        [a, b, scale, mode] => (as_decimal(a)?, as_decimal(b)?, as_scale(scale)?, as_rounding_mode(mode)?),
+        _ => return Err(invalid_parameters(parameters, "decimal_div a b scale [rounding]")),
+    };
+
+    a.divide(&b, scale, mode)
+        .map(|result| Value::String(result.to_string()))
+        .ok_or_else(|| SyntaxError::new(EvaluationError::InvalidValues {
+            description: Some("division by zero".to_string()),
+            values: parameters.to_vec(),
+        }))
+}
+
+/// Converts a value to a decimal.
+///
+/// Integers are converted exactly and text is parsed.
+/// Floats are rejected, as they have already lost the digits and the scale of the number (eg. `12.50` is `12.5`).
+pub fn as_decimal(value: &Value) -> Result<Decimal, SyntaxError> {
+    let decimal = match value {
+        Value::Integer(value) => Ok(Decimal::from(*value)),
+        Value::Float(number) => Err(format!("float `{}` is not a decimal number, quote decimal literals, eg. \"{}\"", number, number)),
+        Value::String(text) | Value::Safe(text) => text.parse().map_err(|error: ParseDecimalError| error.to_string()),
+        _ => Err(format!("{} is not a decimal number", value.type_name())),
+    };
+
+    decimal.map_err(|description| SyntaxError::new(EvaluationError::InvalidValues {
+        description: Some(description),
+        values: vec![value.clone()],
+    }))
+}
+
+/// Rounds a result with an optional scale and rounding mode.
+fn rounded(decimal: Decimal, rounding: &[Value]) -> Result<Value, SyntaxError> {
+    let result = match rounding {
+        [] => decimal,
+        [scale] => decimal.round(as_scale(scale)?, RoundingMode::HalfUp),
+        [scale, mode, ..] => decimal.round(as_scale(scale)?, as_rounding_mode(mode)?),
+    };
+    Ok(Value::String(result.to_string()))
+}
+
+fn as_scale(value: &Value) -> Result<u32, SyntaxError> {
+    value.as_i64()
+        .and_then(|scale| u32::try_from(scale).ok())
+        .filter(|scale| *scale <= MAX_SCALE)
+        .ok_or_else(|| SyntaxError::new(EvaluationError::InvalidValues {
+            description: Some(format!("scale has to be an integer from 0 to {}", MAX_SCALE)),
+            values: vec![value.clone()],
+        }))
+}
+
+fn as_rounding_mode(value: &Value) -> Result<RoundingMode, SyntaxError> {
+    value.as_str()
+        .and_then(RoundingMode::from_name)
+        .ok_or_else(|| SyntaxError::new(EvaluationError::InvalidValues {
+            description: Some(format!("unknown rounding mode `{}`, expected \"half_up\", \"half_even\" or \"truncate\"", value)),
+            values: vec![value.clone()],
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use rubble_templates_core::evaluator::{Context, EvaluationError, SyntaxError};
+    use rubble_templates_core::value::Value;
+    use crate::std_fun::decimal::{decimal_add_function, decimal_div_function, decimal_function, decimal_mul_function, decimal_sub_function, MAX_SCALE};
+
+    fn text(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+
+    fn description(result: Result<Value, SyntaxError>) -> Option<String> {
+        match result.map_err(|error| error.description) {
+            Err(EvaluationError::InvalidValues { description, .. }) => description,
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn should_keep_scale_of_quoted_literals() {
+        let mut context = Context::empty();
+
+        assert_eq!(decimal_function(&[text("12.50")], &mut context), Ok(text("12.50")));
+        assert_eq!(decimal_add_function(&[text("0.10"), text("0.20")], &mut context), Ok(text("0.30")));
+        assert_eq!(decimal_add_function(&[text("19.990"), Value::Integer(1)], &mut context), Ok(text("20.990")));
+    }
+
+    #[test]
+    fn should_keep_digits_of_long_literals() {
+        let mut context = Context::empty();
+
+        assert_eq!(
+            decimal_add_function(&[text("12345678901234567.89"), Value::Integer(0)], &mut context),
+            Ok(text("12345678901234567.89"))
+        );
+        assert_eq!(
+            decimal_add_function(&[text("0.1000000000000000000000001"), text("-0.1")], &mut context),
+            Ok(text("0.0000000000000000000000001"))
+        );
+    }
+
+    #[test]
+    fn should_reject_floats() {
+        let mut context = Context::empty();
+
+        assert_eq!(
+            description(decimal_function(&[Value::Float(12.5)], &mut context)),
+            Some("float `12.5` is not a decimal number, quote decimal literals, eg. \"12.5\"".to_string())
+        );
+        assert_eq!(
+            description(decimal_add_function(&[Value::Integer(1), Value::Float(1.0e20)], &mut context)),
+            Some("float `100000000000000000000` is not a decimal number, quote decimal literals, eg. \"100000000000000000000\"".to_string())
+        );
+    }
+
+    #[test]
+    fn should_limit_scale() {
+        let mut context = Context::empty();
+        let max_scale = Value::Integer(i64::from(MAX_SCALE));
+        let expected = Some(format!("scale has to be an integer from 0 to {}", MAX_SCALE));
+
+        let result = decimal_div_function(&[Value::Integer(1), Value::Integer(3), max_scale], &mut context);
+        assert_eq!(result.map(|value| value.to_string().len()), Ok(usize::try_from(MAX_SCALE).unwrap() + 2));
+
+        for scale in &[-1, i64::from(MAX_SCALE) + 1, 4_000_000_000] {
+            let result = decimal_div_function(&[Value::Integer(1), Value::Integer(3), Value::Integer(*scale)], &mut context);
+            assert_eq!(description(result), expected, "{}", scale);
+            let result = decimal_function(&[Value::Integer(1), Value::Integer(*scale)], &mut context);
+            assert_eq!(description(result), expected, "{}", scale);
+        }
+    }
+
+    #[test]
+    fn should_calculate_exactly() {
+        let mut context = Context::empty();
+
+        assert_eq!(decimal_add_function(&[text("0.1"), text("0.2")], &mut context), Ok(text("0.3")));
+        assert_eq!(decimal_sub_function(&[text("0.3"), text("0.1")], &mut context), Ok(text("0.2")));
+        assert_eq!(decimal_mul_function(&[text("19.99"), Value::Integer(3)], &mut context), Ok(text("59.97")));
+        assert_eq!(decimal_mul_function(&[text("59.97"), text("1.23"), Value::Integer(2)], &mut context), Ok(text("73.76")));
+        assert_eq!(decimal_div_function(&[Value::Integer(1), Value::Integer(3), Value::Integer(20)], &mut context),
+                   Ok(text("0.33333333333333333333")));
+    }
+
+    #[test]
+    fn should_round_with_rounding_modes() {
+        let mut context = Context::empty();
+        let cases = [
+            ("0.125", "half_up", "0.13"),
+            ("0.125", "half_even", "0.12"),
+            ("0.135", "half_even", "0.14"),
+            ("0.129", "truncate", "0.12"),
+            ("-0.125", "half_up", "-0.13"),
+            ("-0.125", "half_even", "-0.12"),
+            ("-0.129", "truncate", "-0.12"),
+        ];
+
+        for (value, mode, expected) in cases.iter() {
+            let result = decimal_function(&[text(value), Value::Integer(2), text(mode)], &mut context);
+            assert_eq!(result, Ok(text(expected)), "{} {}", value, mode);
+        }
+        assert_eq!(decimal_function(&[text("0.125"), Value::Integer(2)], &mut context), Ok(text("0.13")));
+    }
+
+    #[test]
+    fn should_reject_invalid_values() {
+        let mut context = Context::empty();
+        let cases = [
+            (vec![text("1"), Value::Integer(0), Value::Integer(2)], "division by zero"),
+            (vec![text("1"), text("0.00"), Value::Integer(2)], "division by zero"),
+            (vec![Value::Integer(1), Value::Integer(3)], "expected `decimal_div a b scale [rounding]`"),
+            (vec![text("1e5"), Value::Integer(1), Value::Integer(2)], "`1e5` is not a decimal number"),
+            (vec![Value::Bool(true), Value::Integer(1), Value::Integer(2)], "bool is not a decimal number"),
+            (vec![Value::Integer(1), Value::Integer(3), Value::Integer(2), text("up")],
+             "unknown rounding mode `up`, expected \"half_up\", \"half_even\" or \"truncate\""),
+        ];
+
+        for (parameters, expected) in cases.iter() {
+            let result = decimal_div_function(parameters, &mut context);
+            assert_eq!(description(result), Some(expected.to_string()), "{:?}", parameters);
+        }
+    }
+}
@@ -0,0 +1,490 @@
+//! Provides standard comparison and boolean functions.
+//!
+//! See [`std_fun`](rubble-templates::std_fun) or [`logic_functions`] for more info.
+//!
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use rubble_templates_core::ast::SyntaxNode;
+use rubble_templates_core::evaluator::{Function, Context, SyntaxError, EvaluationError, Evaluator};
+use rubble_templates_core::functions::{SimpleFunction, FunctionWithContext, FunctionWithAst};
+use rubble_templates_core::value::Value;
+
+/// Provides a set of comparison and boolean functions.
+///
+/// Available functions:
+/// * [`==`](equal_function) - Checks whether the parameters are equal.
+/// * [`!=`](not_equal_function) - Checks whether the parameters are not equal.
+/// * [`<`](less_function), [`<=`](less_or_equal_function), [`>`](greater_function), [`>=`](greater_or_equal_function) - Compares the parameters.
+/// * [`and`](and_function) - Checks whether all parameters are true, stops at the first false one.
+/// * [`or`](or_function) - Checks whether any parameter is true, stops at the first true one.
+/// * [`not`](not_function) - Negates the parameter.
+/// * [`choose`](choose_function) or `if` - Evaluates only one of the branches, depending on the condition.
+///
+/// Parameters are true or false as defined by [Value::is_truthy]:
+/// null, `false`, zero, empty strings, empty lists and empty maps are false, all other values are true.
+pub fn logic_functions() -> HashMap<String, Box<dyn Function>> {
+    let mut functions: HashMap<String, Box<dyn Function>> = HashMap::new();
+    functions.insert("==".to_string(), FunctionWithContext::new(equal_function));
+    functions.insert("!=".to_string(), FunctionWithContext::new(not_equal_function));
+    functions.insert("<".to_string(), FunctionWithContext::new(less_function));
+    functions.insert("<=".to_string(), FunctionWithContext::new(less_or_equal_function));
+    functions.insert(">".to_string(), FunctionWithContext::new(greater_function));
+    functions.insert(">=".to_string(), FunctionWithContext::new(greater_or_equal_function));
+    functions.insert("and".to_string(), FunctionWithAst::new(and_function));
+    functions.insert("or".to_string(), FunctionWithAst::new(or_function));
+    functions.insert("not".to_string(), SimpleFunction::new(not_function));
+    functions.insert("choose".to_string(), FunctionWithAst::new(choose_function));
+    functions.insert("if".to_string(), FunctionWithAst::new(choose_function));
+    functions
+}
+
+/// Checks whether all parameters are equal.
+///
+/// Values are compared as follows:
+///
+/// | Parameters          | Compared as                                                                     |
+/// |---------------------|---------------------------------------------------------------------------------|
+/// | number and number   | numbers, so `1` is equal to `1.0`                                               |
+/// | string and string   | text, so `"1"` is not equal to `"1.0"`                                          |
+/// | number and string   | numbers if the string is a number (eg. a numeric variable), otherwise not equal |
+/// | list and list       | lists of the same length with equal items (compared by these rules)             |
+/// | map and map         | maps with the same keys and equal values (compared by these rules)              |
+/// | other values        | equal only if they have the same type and value, eg. `true` is not equal to `1` |
+///
+/// As strings are compared with each other as text, `1` is equal to both `"1"` and `"1.0"`,
+/// but `"1"` is not equal to `"1.0"`. That is why every parameter is compared with every other one,
+/// so the result does not depend on the order of parameters, eg. both `== 1 "1" "1.0"` and `== "1" 1 "1.0"` are false.
+///
+/// Eg.
+/// ```text
+/// == 1 1.0
+/// == "1" 1
+/// == "1.0" "1"
+/// == 1 "a"
+/// == "a" "a" "b"
+/// ```
+/// Expected output:
+/// ```text
+/// true
+/// true
+/// false
+/// false
+/// false
+/// ```
+pub fn equal_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    expect_pairs(parameters)?;
+    let all_equal = parameters.iter().enumerate()
+        .all(|(index, a)| parameters[index + 1..].iter().all(|b| values_equal(a, b)));
+    Ok(Value::Bool(all_equal))
+}
+
+/// Checks whether the parameters are not equal, this is the negation of [`==`](equal_function).
+///
+/// Eg.
+/// ```text
+/// != 1 2
+/// != "a" "a"
+/// ```
+/// Expected output:
+/// ```text
+/// true
+/// false
+/// ```
+pub fn not_equal_function(parameters: &[Value], context: &mut Context) -> Result<Value, SyntaxError> {
+    let equal = equal_function(parameters, context)?;
+    Ok(Value::Bool(!equal.is_truthy()))
+}
+
+/// Checks whether each parameter is less than the next one.
+///
+/// Numbers are compared by their values and strings are compared lexicographically.
+/// A string is compared with a number as a number, like in [`==`](equal_function).
+/// Other values, including strings that are not numbers compared with numbers, cannot be compared and result in an error.
+///
+/// Eg.
+/// ```text
+/// < 1 2.5 3
+/// < "b" "a"
+/// ```
+/// Expected output:
+/// ```text
+/// true
+/// false
+/// ```
+pub fn less_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    check_pairs(parameters, |a, b| compare_values(a, b).map(|it| it == Ordering::Less))
+}
+
+/// Checks whether each parameter is less than or equal to the next one (see [`<`](less_function)).
+///
+/// Eg.
+/// ```text
+/// <= 1 1 2
+/// ```
+/// Expected output:
+/// ```text
+/// true
+/// ```
+pub fn less_or_equal_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    check_pairs(parameters, |a, b| compare_values(a, b).map(|it| it != Ordering::Greater))
+}
+
+/// Checks whether each parameter is greater than the next one (see [`<`](less_function)).
+///
+/// Eg.
+/// ```text
+/// > 3 2 1
+/// > "abc" "abd"
+/// ```
+/// Expected output:
+/// ```text
+/// true
+/// false
+/// ```
+pub fn greater_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    check_pairs(parameters, |a, b| compare_values(a, b).map(|it| it == Ordering::Greater))
+}
+
+/// Checks whether each parameter is greater than or equal to the next one (see [`<`](less_function)).
+///
+/// Eg.
+/// ```text
+/// >= 2 2.0 1
+/// ```
+/// Expected output:
+/// ```text
+/// true
+/// ```
+pub fn greater_or_equal_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    check_pairs(parameters, |a, b| compare_values(a, b).map(|it| it != Ordering::Less))
+}
+
+/// Checks whether all parameters are true.
+///
+/// Parameters are evaluated one by one and the evaluation stops at the first false parameter,
+/// so the rest of them is never evaluated.
+///
+/// Eg.
+/// ```text
+/// and true 1 "text"
+/// and false (unknown_function)
+/// ```
+/// Expected output:
+/// ```text
+/// true
+/// false
+/// ```
+pub fn and_function(evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<Value, SyntaxError> {
+    for parameter in parameters {
+        if !evaluator.evaluate(parameter, context)?.is_truthy() {
+            return Ok(Value::Bool(false));
+        }
+    }
+    Ok(Value::Bool(true))
+}
+
+/// Checks whether any parameter is true.
+///
+/// Parameters are evaluated one by one and the evaluation stops at the first true parameter,
+/// so the rest of them is never evaluated.
+///
+/// Eg.
+/// ```text
+/// or 0 "" "text"
+/// or true (unknown_function)
+/// ```
+/// Expected output:
+/// ```text
+/// true
+/// true
+/// ```
+pub fn or_function(evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<Value, SyntaxError> {
+    for parameter in parameters {
+        if evaluator.evaluate(parameter, context)?.is_truthy() {
+            return Ok(Value::Bool(true));
+        }
+    }
+    Ok(Value::Bool(false))
+}
+
+/// Negates the parameter. If there is more than one parameter, then checks whether all of them are false.
+///
+/// Eg.
+/// ```text
+/// not ""
+/// not 1
+/// ```
+/// Expected output:
+/// ```text
+/// true
+/// false
+/// ```
+pub fn not_function(parameters: &[Value]) -> Value {
+    Value::Bool(!parameters.iter().any(Value::is_truthy))
+}
+
+/// Evaluates the second parameter if the first one is true, otherwise evaluates the third one (if present).
+///
+/// Only one of the branches is evaluated. When the condition is false and there is no third parameter, the result is null.
+///
+/// It is also available as `if`, but only in nested calls (eg. `concat (if admin "Admin" "User")`),
+/// because an `if` at the beginning of a code block starts an `if` block. Use `choose` at the beginning of a code block.
+///
+/// Eg.
+/// ```text
+/// choose (> 2 1) "yes" "no"
+/// choose "" "yes"
+/// concat "[" (if (< 2 1) "yes" "no") "]"
+/// ```
+/// Expected output:
+/// ```text
+/// yes
+///
+/// [no]
+/// ```
+pub fn choose_function(evaluator: &dyn Evaluator, parameters: &[SyntaxNode], context: &mut Context) -> Result<Value, SyntaxError> {
+    let (condition, then, otherwise) = match parameters {
+        [condition, then] => (condition, then, None),
+        [condition, then, otherwise] => (condition, then, Some(otherwise)),
+        _ => return Err(SyntaxError::new(EvaluationError::InvalidArguments {
+            description: Some("Expected `choose condition value` or `choose condition value other_value`".to_string()),
+            arguments: parameters.to_vec(),
+        })),
+    };
+
+    if evaluator.evaluate(condition, context)?.is_truthy() {
+        evaluator.evaluate(then, context)
+    } else {
+        otherwise.map(|it| evaluator.evaluate(it, context))
+            .unwrap_or(Ok(Value::Null))
+    }
+}
+
+/// Checks whether two values are equal, see [`==`](equal_function).
+pub fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::List(a), Value::List(b)) =>
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b)),
+        (Value::Map(a), Value::Map(b)) =>
+            a.len() == b.len() && a.iter().all(|(key, a)| b.get(key).is_some_and(|b| values_equal(a, b))),
+        _ => match ordering_of(a, b) {
+            Some(ordering) => ordering == Ordering::Equal,
+            None => a == b,
+        },
+    }
+}
+
+/// Compares two values, see [`<`](less_function).
+///
+/// Returns an [EvaluationError::InvalidValues] with both values if they cannot be compared.
+pub fn compare_values(a: &Value, b: &Value) -> Result<Ordering, SyntaxError> {
+    ordering_of(a, b).ok_or_else(|| SyntaxError::new(EvaluationError::InvalidValues {
+        description: Some(format!("cannot compare {} with {}", a.type_name(), b.type_name())),
+        values: vec![a.clone(), b.clone()],
+    }))
+}
+
+fn ordering_of(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+        (Value::Integer(_), Value::Float(_)) | (Value::Float(_), Value::Integer(_)) | (Value::Float(_), Value::Float(_)) =>
+            a.as_f64()?.partial_cmp(&b.as_f64()?),
+        _ => match (a.as_str(), b.as_str()) {
+            (Some(a), Some(b)) => Some(a.cmp(b)),
+            (Some(text), None) if b.as_f64().is_some() => ordering_of(&number_of(text)?, b),
+            (None, Some(text)) if a.as_f64().is_some() => ordering_of(a, &number_of(text)?),
+            _ => None,
+        },
+    }
+}
+
+/// Parses a string compared with a number, integers are kept as integers so they are compared exactly.
+fn number_of(text: &str) -> Option<Value> {
+    text.parse::<i64>().map(Value::Integer).ok()
+        .or_else(|| text.parse::<f64>().map(Value::Float).ok())
+}
+
+fn check_pairs<F>(parameters: &[Value], check: F) -> Result<Value, SyntaxError>
+    where F: Fn(&Value, &Value) -> Result<bool, SyntaxError> {
+    expect_pairs(parameters)?;
+
+    for pair in parameters.windows(2) {
+        if !check(&pair[0], &pair[1])? {
+            return Ok(Value::Bool(false));
+        }
+    }
+    Ok(Value::Bool(true))
+}
+
+fn expect_pairs(parameters: &[Value]) -> Result<(), SyntaxError> {
+    if parameters.len() < 2 {
+        return Err(SyntaxError::new(EvaluationError::InvalidValues {
+            description: Some(format!("expected at least 2 values, got {}", parameters.len())),
+            values: parameters.to_vec(),
+        }));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::collections::BTreeMap;
+    use rubble_templates_core::ast::SyntaxNode;
+    use rubble_templates_core::evaluator::{Context, EvaluationError, SyntaxError};
+    use rubble_templates_core::value::Value;
+    use rubble_templates_evaluators::parser::parse_ast;
+    use rubble_templates_evaluators::simple::evaluator::SimpleEvaluationEngine;
+    use rubble_templates_core::evaluator::Evaluator;
+    use crate::std_fun::logic::{and_function, choose_function, compare_values, equal_function, logic_functions, not_equal_function, or_function, values_equal};
+
+    fn text(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+
+    fn parameters(code: &str) -> Vec<SyntaxNode> {
+        match parse_ast(code, "{{", "}}") {
+            Ok(SyntaxNode::NamedNode { children, .. }) => children,
+            other => panic!("expected a function call, got {:?}", other),
+        }
+    }
+
+    fn description(result: Result<Ordering, SyntaxError>) -> Option<String> {
+        match result.map_err(|error| error.description) {
+            Err(EvaluationError::InvalidValues { description, .. }) => description,
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn should_compare_numbers_by_value() {
+        assert!(values_equal(&Value::Integer(1), &Value::Float(1.0)));
+        assert!(values_equal(&Value::Float(2.5), &Value::Float(2.5)));
+        assert!(!values_equal(&Value::Integer(1), &Value::Integer(2)));
+        assert!(!values_equal(&Value::Float(f64::NAN), &Value::Float(f64::NAN)));
+        assert_eq!(compare_values(&Value::Integer(1), &Value::Float(1.5)).ok(), Some(Ordering::Less));
+        assert_eq!(compare_values(&Value::Integer(i64::MAX), &Value::Integer(i64::MAX - 1)).ok(), Some(Ordering::Greater));
+    }
+
+    #[test]
+    fn should_compare_strings_as_text() {
+        assert!(values_equal(&text("1"), &Value::Safe("1".to_string())));
+        assert!(!values_equal(&text("1"), &text("1.0")));
+        assert!(!values_equal(&text("01"), &text("1")));
+        assert_eq!(compare_values(&text("10"), &text("9")).ok(), Some(Ordering::Less));
+    }
+
+    #[test]
+    fn should_compare_numeric_strings_with_numbers_as_numbers() {
+        assert!(values_equal(&Value::Integer(1), &text("1")));
+        assert!(values_equal(&text("1.0"), &Value::Integer(1)));
+        assert!(values_equal(&Value::Integer(9007199254740993), &text("9007199254740993")));
+        assert!(!values_equal(&Value::Integer(9007199254740992), &text("9007199254740993")));
+        assert_eq!(compare_values(&text("10"), &Value::Integer(9)).ok(), Some(Ordering::Greater));
+        assert_eq!(compare_values(&Value::Float(2.5), &text("3")).ok(), Some(Ordering::Less));
+    }
+
+    #[test]
+    fn should_not_compare_other_strings_with_numbers() {
+        assert!(!values_equal(&Value::Integer(1), &text("a")));
+        assert!(!values_equal(&text(""), &Value::Integer(0)));
+        assert_eq!(description(compare_values(&Value::Integer(1), &text("a"))),
+                   Some("cannot compare integer with string".to_string()));
+        assert_eq!(description(compare_values(&text("a"), &Value::Float(1.0))),
+                   Some("cannot compare string with float".to_string()));
+    }
+
+    #[test]
+    fn should_compare_lists_and_maps_by_items() {
+        let list = |values: Vec<Value>| Value::List(values);
+        let map = |entries: Vec<(&str, Value)>| Value::Map(entries.into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect::<BTreeMap<_, _>>());
+
+        assert!(values_equal(&list(vec![Value::Integer(1), text("2")]), &list(vec![Value::Float(1.0), Value::Integer(2)])));
+        assert!(!values_equal(&list(vec![Value::Integer(1)]), &list(vec![Value::Integer(1), Value::Integer(1)])));
+        assert!(values_equal(&map(vec![("a", Value::Integer(1))]), &map(vec![("a", Value::Float(1.0))])));
+        assert!(!values_equal(&map(vec![("a", Value::Integer(1))]), &map(vec![("b", Value::Integer(1))])));
+        assert!(!values_equal(&map(vec![("a", Value::Integer(1))]), &map(vec![("a", Value::Integer(1)), ("b", Value::Null)])));
+        assert!(description(compare_values(&list(vec![]), &list(vec![]))).is_some());
+    }
+
+    #[test]
+    fn should_compare_other_values_by_type_and_value() {
+        assert!(values_equal(&Value::Null, &Value::Null));
+        assert!(values_equal(&Value::Bool(true), &Value::Bool(true)));
+        assert!(!values_equal(&Value::Bool(true), &Value::Integer(1)));
+        assert!(!values_equal(&Value::Null, &text("")));
+        assert!(!values_equal(&Value::Bool(false), &text("false")));
+        assert_eq!(description(compare_values(&Value::Bool(false), &Value::Bool(true))),
+                   Some("cannot compare bool with bool".to_string()));
+    }
+
+    #[test]
+    fn should_compare_all_parameters() {
+        let mut context = Context::empty();
+
+        assert_eq!(equal_function(&[Value::Integer(1), text("1"), Value::Float(1.0)], &mut context).ok(), Some(Value::Bool(true)));
+        assert_eq!(equal_function(&[text("a"), text("a"), text("b")], &mut context).ok(), Some(Value::Bool(false)));
+        assert!(equal_function(&[text("a")], &mut context).is_err());
+    }
+
+    #[test]
+    fn should_not_depend_on_order_of_parameters() {
+        let mut context = Context::empty();
+        // equality of mixed strings and numbers is not transitive
+        assert!(values_equal(&Value::Integer(1), &text("1")));
+        assert!(values_equal(&Value::Integer(1), &text("1.0")));
+        assert!(!values_equal(&text("1"), &text("1.0")));
+
+        let orders = [
+            [Value::Integer(1), text("1"), text("1.0")],
+            [text("1"), Value::Integer(1), text("1.0")],
+            [text("1"), text("1.0"), Value::Integer(1)],
+        ];
+        for parameters in orders.iter() {
+            assert_eq!(equal_function(parameters, &mut context).ok(), Some(Value::Bool(false)), "{:?}", parameters);
+            assert_eq!(not_equal_function(parameters, &mut context).ok(), Some(Value::Bool(true)), "{:?}", parameters);
+        }
+    }
+
+    #[test]
+    fn should_evaluate_only_used_branches() {
+        let engine = SimpleEvaluationEngine::from(logic_functions());
+        let mut context = Context::empty();
+        context.set_variable("count", "3");
+
+        assert_eq!(choose_function(&engine, &parameters("{{ choose (== count 3) \"three\" (unknown) }}"), &mut context).ok(), Some(text("three")));
+        assert_eq!(choose_function(&engine, &parameters("{{ choose (> count 5) (unknown) \"few\" }}"), &mut context).ok(), Some(text("few")));
+        assert_eq!(choose_function(&engine, &parameters("{{ choose \"\" (unknown) }}"), &mut context).ok(), Some(Value::Null));
+        assert_eq!(or_function(&engine, &parameters("{{ or 0 count (unknown) }}"), &mut context).ok(), Some(Value::Bool(true)));
+        assert_eq!(and_function(&engine, &parameters("{{ and count false (unknown) }}"), &mut context).ok(), Some(Value::Bool(false)));
+        assert!(and_function(&engine, &parameters("{{ and count (unknown) }}"), &mut context).is_err());
+    }
+
+    #[test]
+    fn should_reject_choose_without_branches() {
+        let engine = SimpleEvaluationEngine::from(logic_functions());
+        let mut context = Context::empty();
+
+        for code in ["{{ choose true }}", "{{ choose true 1 2 3 }}"].iter() {
+            let result = choose_function(&engine, &parameters(code), &mut context);
+
+            assert!(matches!(result.map_err(|error| error.description), Err(EvaluationError::InvalidArguments { description: Some(description), .. })
+                if description == "Expected `choose condition value` or `choose condition value other_value`"), "{}", code);
+        }
+    }
+
+    #[test]
+    fn should_provide_if_in_nested_calls() {
+        let engine = SimpleEvaluationEngine::from(logic_functions());
+        let mut context = Context::empty();
+        context.set_variable("admin", false);
+
+        let code = parse_ast("{{ not (if admin false (unknown)) }}", "{{", "}}").unwrap();
+        assert!(engine.evaluate(&code, &mut context).is_err());
+
+        let code = parse_ast("{{ not (if (not admin) false (unknown)) }}", "{{", "}}").unwrap();
+        assert_eq!(engine.evaluate(&code, &mut context), Ok(Value::Bool(true)));
+    }
+}
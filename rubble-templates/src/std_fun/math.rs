@@ -2,11 +2,13 @@
 //!
 //! See [`std_fun`](rubble-templates::std_fun) or [`math_functions`] for more info.
 //!
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use rubble_templates_core::evaluator::{Function, Context, SyntaxError, EvaluationError};
-use rubble_templates_core::functions::{SimpleFunction, FunctionWithContext};
-use std::num::ParseFloatError;
-use crate::std_fun::strings::EMPTY_STRING;
+use rubble_templates_core::functions::FunctionWithContext;
+use rubble_templates_core::value::Value;
+use crate::std_fun::strings::invalid_parameters;
 
 /// Provides a set of math functions.
 ///
@@ -14,15 +16,45 @@ use crate::std_fun::strings::EMPTY_STRING;
 /// * [`+`](plus_function) - Adds the parameters or concatenates them if it fails.
 /// * [`-`](minus_function) - Subtracts the parameters.
 /// * [`*`](multiply_function) - Multiplies the parameters.
-/// * [`/`](divide_function) - Divides the parameters.
+/// * [`/`](divide_function) - Divides the parameters, integers are rounded towards zero (`/ -7 2` is `-3`).
+/// * [`div`](integer_divide_function) - Divides integers, rounding the result down (`div -7 2` is `-4`).
 /// * [`mod`](modulo_function) - Calculates the remainder.
+/// * [`int`](int_function), [`float`](float_function) - Converts the parameter to an integer or a float.
+/// * [`pow`](pow_function) - Raises the parameter to a power.
+/// * [`sqrt`](sqrt_function) - Calculates the square root.
+/// * [`abs`](abs_function) - Calculates the absolute value.
+/// * [`sign`](sign_function) - Returns the sign of the parameter (`-1`, `0` or `1`).
+/// * [`min`](min_function), [`max`](max_function) - Finds the smallest or the largest parameter.
+/// * [`clamp`](clamp_function) - Restricts the parameter to a range.
+/// * [`round`](round_function) - Rounds the parameter, optionally to a number of decimal places.
+/// * [`floor`](floor_function), [`ceil`](ceil_function) - Rounds the parameter down or up.
+///
+/// Integers are kept as integers as long as it is possible, so eg. `+ 1 2` is `3` and not `3.0`.
+/// This also applies to division, so `/ 1440 8 6 4` is `7` and a float is needed to get a fraction, eg. `/ 1440 8 6 4.0` is `7.5`.
+/// Note that [`/`](divide_function) and [`div`](integer_divide_function) round negative results differently.
+/// When an integer overflows or a number is divided by zero, an [EvaluationError::InvalidValues] is returned.
+/// If any of the parameters is a float, the result is a float.
+/// Numbers passed as text (eg. numeric variables) are parsed.
 pub fn math_functions() -> HashMap<String, Box<dyn Function>> {
     let mut functions: HashMap<String, Box<dyn Function>> = HashMap::new();
-    functions.insert("+".to_string(), SimpleFunction::new(plus_function));
+    functions.insert("+".to_string(), FunctionWithContext::new(plus_function));
     functions.insert("-".to_string(), FunctionWithContext::new(minus_function));
     functions.insert("*".to_string(), FunctionWithContext::new(multiply_function));
     functions.insert("/".to_string(), FunctionWithContext::new(divide_function));
+    functions.insert("div".to_string(), FunctionWithContext::new(integer_divide_function));
     functions.insert("mod".to_string(), FunctionWithContext::new(modulo_function));
+    functions.insert("int".to_string(), FunctionWithContext::new(int_function));
+    functions.insert("float".to_string(), FunctionWithContext::new(float_function));
+    functions.insert("pow".to_string(), FunctionWithContext::new(pow_function));
+    functions.insert("sqrt".to_string(), FunctionWithContext::new(sqrt_function));
+    functions.insert("abs".to_string(), FunctionWithContext::new(abs_function));
+    functions.insert("sign".to_string(), FunctionWithContext::new(sign_function));
+    functions.insert("min".to_string(), FunctionWithContext::new(min_function));
+    functions.insert("max".to_string(), FunctionWithContext::new(max_function));
+    functions.insert("clamp".to_string(), FunctionWithContext::new(clamp_function));
+    functions.insert("round".to_string(), FunctionWithContext::new(round_function));
+    functions.insert("floor".to_string(), FunctionWithContext::new(floor_function));
+    functions.insert("ceil".to_string(), FunctionWithContext::new(ceil_function));
     functions
 }
 
@@ -31,40 +63,31 @@ pub fn math_functions() -> HashMap<String, Box<dyn Function>> {
 ///
 /// Eg.
 /// ```text
+/// + 1 2 3
 /// + 1 2 3.3
 /// + 1 2 "hello" 3.3
 /// ```
 /// Expected output:
 /// ```text
+/// 6
 /// 6.3
 /// 3hello3.3
 /// ```
-pub fn plus_function(parameters: &[String]) -> String {
-    let mut result: String = EMPTY_STRING.to_string();
-    let mut floating_result: Option<f64> = None;
-
-    parameters.iter().for_each(|param| {
-        if result.is_empty() {
-            if let Result::Ok(value) = param.parse::<f64>() {
-                floating_result = Some(floating_result.unwrap_or(0 as f64) + value);
-            } else {
-                if let Some(number) = floating_result
-                    .map(|number| number.to_string()) {
-                    result += &number
-                }
+pub fn plus_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let mut sum = Number::Integer(0);
 
-                result.push_str(param);
+    for (index, parameter) in parameters.iter().enumerate() {
+        match number_of(parameter) {
+            Some(number) => sum = sum.apply(number, i64::checked_add, |a, b| a + b)?,
+            None => {
+                let mut result = if index > 0 { sum.to_value().to_string() } else { String::new() };
+                parameters[index..].iter().for_each(|param| result.push_str(&param.to_string()));
+                return Ok(Value::String(result));
             }
-        } else {
-            result.push_str(param);
-        };
-    });
-
-    if result.is_empty() && floating_result.is_some() {
-        floating_result.map(|number| number.to_string()).unwrap()
-    } else {
-        result
+        }
     }
+
+    Ok(sum.to_value())
 }
 
 /// Subtracts values.
@@ -72,14 +95,16 @@ pub fn plus_function(parameters: &[String]) -> String {
 ///
 /// Eg.
 /// ```text
+/// - 8 1 4
 /// - 8.3 1 1.4
 /// ```
 /// Expected output:
 /// ```text
+/// 3
 /// 5.9
 /// ```
-pub fn minus_function(parameters: &[String], _context: &mut Context) -> Result<String, SyntaxError> {
-    reduce_numbers(parameters, |a, b| a - b)
+pub fn minus_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    reduce(parameters, |a, b| a.apply(b, i64::checked_sub, |a, b| a - b))
 }
 
 /// Multiplies values.
@@ -87,29 +112,79 @@ pub fn minus_function(parameters: &[String], _context: &mut Context) -> Result<S
 ///
 /// Eg.
 /// ```text
+/// * 4 2 7
 /// * 4 2 7.2
 /// ```
 /// Expected output:
 /// ```text
+/// 56
 /// 57.6
 /// ```
-pub fn multiply_function(parameters: &[String], _context: &mut Context) -> Result<String, SyntaxError> {
-    reduce_numbers(parameters, |a, b| a * b)
+pub fn multiply_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    reduce(parameters, |a, b| a.apply(b, i64::checked_mul, |a, b| a * b))
 }
 
 /// Divides values.
 /// If any of the parameters is not convertible to a number, then an error will be emitted with the invalid value.
 ///
+/// Integers are always divided into an integer, discarding the remainder (the result is rounded towards zero).
+/// If any of the parameters is a float, the result is a float, so [`float`](float_function) can be used to get a fraction.
+///
+/// Eg.
+/// ```text
+/// / 1440 8 6
+/// / 10 3
+/// / -7 2
+/// / 10 4.0
+/// / (float 10) 4
+/// / 10 0
+/// ```
+/// Expected output:
+/// ```text
+/// 30
+/// 3
+/// -3
+/// 2.5
+/// 2.5
+/// (an error: division by zero)
+/// ```
+pub fn divide_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    reduce(parameters, |a, b| if b.is_zero() {
+        Err(division_by_zero(parameters))
+    } else {
+        a.apply(b, i64::checked_div, |a, b| a / b)
+    })
+}
+
+/// Divides integers, rounding the result down (towards negative infinity), unlike [`/`](divide_function)
+/// that rounds it towards zero.
+/// If any of the parameters is not an integer, then an error will be emitted with the invalid value.
+///
 /// Eg.
 /// ```text
-/// / 1440 8 6 4
+/// div 10 3
+/// div -7 2
 /// ```
 /// Expected output:
 /// ```text
-/// 7.5
+/// 3
+/// -4
 /// ```
-pub fn divide_function(parameters: &[String], _context: &mut Context) -> Result<String, SyntaxError> {
-    reduce_numbers(parameters, |a, b| a / b)
+pub fn integer_divide_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let integers = parameters.iter()
+        .map(as_integer)
+        .collect::<Result<Vec<i64>, SyntaxError>>()?;
+
+    let mut values = integers.into_iter();
+    let mut result = values.next().unwrap_or(0);
+    for value in values {
+        if value == 0 {
+            return Err(division_by_zero(parameters));
+        }
+        let quotient = result.checked_div(value).ok_or_else(|| overflow(parameters))?;
+        result = if result % value != 0 && (result < 0) != (value < 0) { quotient - 1 } else { quotient };
+    }
+    Ok(Value::Integer(result))
 }
 
 /// Calculates the remainder (modulo).
@@ -118,43 +193,653 @@ pub fn divide_function(parameters: &[String], _context: &mut Context) -> Result<
 /// Eg.
 /// ```text
 /// mod 7 4
+/// mod 7.5 2
+/// ```
+/// Expected output:
+/// ```text
+/// 3
+/// 1.5
+/// ```
+pub fn modulo_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    reduce(parameters, |a, b| if b.is_zero() {
+        Err(division_by_zero(parameters))
+    } else {
+        a.apply(b, i64::checked_rem, |a, b| a % b)
+    })
+}
+
+/// Converts the parameter to an integer.
+///
+/// Floats are truncated (rounded towards zero), text is parsed and booleans are converted to `1` or `0`.
+///
+/// Eg.
+/// ```text
+/// int 3.9
+/// int "-12"
+/// int true
+/// ```
+/// Expected output:
+/// ```text
+/// 3
+/// -12
+/// 1
+/// ```
+pub fn int_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let number = match parameters {
+        [Value::Bool(value)] => Number::Integer(*value as i64),
+        [value] => as_number_value(value)?,
+        _ => return Err(invalid_parameters(parameters, "int value")),
+    };
+
+    match number {
+        Number::Integer(value) => Ok(Value::Integer(value)),
+        Number::Float(value) => float_to_integer(value.trunc())
+            .map(Value::Integer)
+            .ok_or_else(|| SyntaxError::new(EvaluationError::InvalidValues {
+                description: Some(format!("{} cannot be converted to an integer", value)),
+                values: parameters.to_vec(),
+            })),
+    }
+}
+
+/// Converts the parameter to a float.
+///
+/// Text is parsed and booleans are converted to `1.0` or `0.0`.
+///
+/// Eg.
+/// ```text
+/// / (float 1) 4
+/// float "2.5"
+/// ```
+/// Expected output:
+/// ```text
+/// 0.25
+/// 2.5
+/// ```
+pub fn float_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    match parameters {
+        [Value::Bool(value)] => Ok(Value::Float(*value as i64 as f64)),
+        [value] => Ok(Value::Float(as_number_value(value)?.as_f64())),
+        _ => Err(invalid_parameters(parameters, "float value")),
+    }
+}
+
+/// Raises the first parameter to the power of the second one.
+///
+/// An integer raised to a non-negative integer power is an integer, otherwise the result is a float.
+///
+/// Eg.
+/// ```text
+/// pow 2 10
+/// pow 2 -1
+/// pow 9 0.5
+/// ```
+/// Expected output:
+/// ```text
+/// 1024
+/// 0.5
+/// 3
+/// ```
+pub fn pow_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let (base, exponent) = match parameters {
+        [base, exponent] => (as_number_value(base)?, as_number_value(exponent)?),
+        _ => return Err(invalid_parameters(parameters, "pow base exponent")),
+    };
+
+    match (base, exponent) {
+        (Number::Integer(base), Number::Integer(exponent)) if exponent >= 0 => u32::try_from(exponent).ok()
+            .and_then(|exponent| base.checked_pow(exponent))
+            .map(Value::Integer)
+            .ok_or_else(|| overflow(parameters)),
+        (base, exponent) => Ok(Value::Float(base.as_f64().powf(exponent.as_f64()))),
+    }
+}
+
+/// Calculates the square root. The result is always a float.
+///
+/// Eg.
+/// ```text
+/// sqrt 16
+/// sqrt 2.25
+/// ```
+/// Expected output:
+/// ```text
+/// 4
+/// 1.5
+/// ```
+pub fn sqrt_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let value = match parameters {
+        [value] => as_number_value(value)?.as_f64(),
+        _ => return Err(invalid_parameters(parameters, "sqrt value")),
+    };
+
+    if value < 0.0 {
+        return Err(SyntaxError::new(EvaluationError::InvalidValues {
+            description: Some("square root of a negative number".to_string()),
+            values: parameters.to_vec(),
+        }));
+    }
+    Ok(Value::Float(value.sqrt()))
+}
+
+/// Calculates the absolute value.
+///
+/// Eg.
+/// ```text
+/// abs -5
+/// abs -2.5
+/// ```
+/// Expected output:
+/// ```text
+/// 5
+/// 2.5
+/// ```
+pub fn abs_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    match parameters {
+        [value] => match as_number_value(value)? {
+            Number::Integer(value) => value.checked_abs().map(Value::Integer).ok_or_else(|| overflow(parameters)),
+            Number::Float(value) => Ok(Value::Float(value.abs())),
+        },
+        _ => Err(invalid_parameters(parameters, "abs value")),
+    }
+}
+
+/// Returns `-1` for negative numbers, `0` for zero and `1` for positive numbers.
+///
+/// Eg.
+/// ```text
+/// sign -5
+/// sign 0.0
+/// sign 0.5
+/// ```
+/// Expected output:
+/// ```text
+/// -1
+/// 0
+/// 1
+/// ```
+pub fn sign_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let number = match parameters {
+        [value] => as_number_value(value)?,
+        _ => return Err(invalid_parameters(parameters, "sign value")),
+    };
+
+    number.compare(Number::Integer(0))
+        .map(|ordering| Value::Integer(ordering as i64))
+        .ok_or_else(|| not_comparable(parameters))
+}
+
+/// Finds the smallest parameter.
+///
+/// Eg.
+/// ```text
+/// min 3 1.5 2
+/// ```
+/// Expected output:
+/// ```text
+/// 1.5
+/// ```
+pub fn min_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    select(parameters, "min value...", Ordering::Less)
+}
+
+/// Finds the largest parameter.
+///
+/// Eg.
+/// ```text
+/// max 3 1.5 2
+/// ```
+/// Expected output:
+/// ```text
+/// 3
+/// ```
+pub fn max_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    select(parameters, "max value...", Ordering::Greater)
+}
+
+/// Restricts the first parameter to a range between the second and the third parameter (inclusive).
+///
+/// Eg.
+/// ```text
+/// clamp 15 0 10
+/// clamp -1 0 10
+/// clamp 2.5 0 10
+/// ```
+/// Expected output:
+/// ```text
+/// 10
+/// 0
+/// 2.5
+/// ```
+pub fn clamp_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let (value, min, max) = match parameters {
+        [value, min, max] => (as_number_value(value)?, as_number_value(min)?, as_number_value(max)?),
+        _ => return Err(invalid_parameters(parameters, "clamp value min max")),
+    };
+
+    match (min.compare(max), value.compare(min), value.compare(max)) {
+        (Some(Ordering::Greater), _, _) => Err(SyntaxError::new(EvaluationError::InvalidValues {
+            description: Some("min is greater than max".to_string()),
+            values: parameters.to_vec(),
+        })),
+        (Some(_), Some(Ordering::Less), _) => Ok(min.to_value()),
+        (Some(_), _, Some(Ordering::Greater)) => Ok(max.to_value()),
+        (Some(_), Some(_), Some(_)) => Ok(value.to_value()),
+        _ => Err(not_comparable(parameters)),
+    }
+}
+
+/// Rounds the parameter to the nearest integer, or to a number of decimal places if the precision is given.
+/// Halfway cases are rounded away from zero.
+///
+/// A negative precision rounds to tens, hundreds etc. Floats that are too large to be rounded
+/// to the given precision (or a precision of 17 and more) are returned unchanged.
+///
+/// Eg.
+/// ```text
+/// round 2.5
+/// round 3.14159 2
+/// round 1234 -2
 /// ```
 /// Expected output:
 /// ```text
 /// 3
+/// 3.14
+/// 1200
+/// ```
+pub fn round_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let (number, precision) = match parameters {
+        [value] => (as_number_value(value)?, 0),
+        [value, precision] => (as_number_value(value)?, as_integer(precision)?),
+        _ => return Err(invalid_parameters(parameters, "round value [precision]")),
+    };
+
+    match number {
+        Number::Integer(_) if precision >= 0 => Ok(number.to_value()),
+        Number::Integer(value) => {
+            // calculated on i128, so a factor that does not fit in i64 (eg. 10^19) still rounds correctly
+            let factor = match u32::try_from(precision.unsigned_abs()).ok().and_then(|exponent| 10_i128.checked_pow(exponent)) {
+                Some(factor) => factor,
+                None => return Ok(Value::Integer(0)),
+            };
+            let value = i128::from(value);
+            let remainder = value % factor;
+            let rounded = if remainder.abs() * 2 >= factor {
+                value - remainder + remainder.signum() * factor
+            } else {
+                value - remainder
+            };
+            i64::try_from(rounded).map(Value::Integer).map_err(|_| overflow(parameters))
+        }
+        Number::Float(value) if precision <= 0 => {
+            let factor = 10_f64.powi(-precision.max(i32::MIN as i64 + 1) as i32);
+            if factor.is_infinite() {
+                return Ok(Value::Integer(0));
+            }
+            let rounded = (value / factor).round() * factor;
+            Ok(float_to_integer(rounded).map(Value::Integer).unwrap_or(Value::Float(rounded)))
+        }
+        // a float has at most 17 significant digits, so there is nothing to round
+        Number::Float(value) if precision >= 17 => Ok(Value::Float(value)),
+        Number::Float(value) => {
+            let factor = 10_f64.powi(precision as i32);
+            let scaled = value * factor;
+            if scaled.is_finite() {
+                Ok(Value::Float(scaled.round() / factor))
+            } else {
+                Ok(Value::Float(value))
+            }
+        }
+    }
+}
+
+/// Rounds the parameter down (to the largest integer that is not greater than it).
+///
+/// Eg.
+/// ```text
+/// floor 2.7
+/// floor -2.5
 /// ```
-pub fn modulo_function(parameters: &[String], _context: &mut Context) -> Result<String, SyntaxError> {
-    reduce_numbers(parameters, |a, b| a % b)
+/// Expected output:
+/// ```text
+/// 2
+/// -3
+/// ```
+pub fn floor_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    match parameters {
+        [value] => Ok(round_number(as_number_value(value)?, f64::floor)),
+        _ => Err(invalid_parameters(parameters, "floor value")),
+    }
+}
+
+/// Rounds the parameter up (to the smallest integer that is not less than it).
+///
+/// Eg.
+/// ```text
+/// ceil 2.1
+/// ceil -2.5
+/// ```
+/// Expected output:
+/// ```text
+/// 3
+/// -2
+/// ```
+pub fn ceil_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    match parameters {
+        [value] => Ok(round_number(as_number_value(value)?, f64::ceil)),
+        _ => Err(invalid_parameters(parameters, "ceil value")),
+    }
+}
+
+/// Converts all parameters to numbers.
+///
+/// Returns an [EvaluationError::InvalidValues] with the first value that is not convertible to a number.
+pub fn as_numbers(parameters: &[Value]) -> Result<Vec<f64>, SyntaxError> {
+    parameters.iter()
+        .map(|value| as_number_value(value).map(|number| number.as_f64()))
+        .collect()
+}
+
+/// Converts a value to a number.
+///
+/// Numbers are returned as they are and strings are parsed, so numeric variables passed as text can still be used.
+pub fn as_number(value: &Value) -> Option<f64> {
+    number_of(value).map(|number| number.as_f64())
+}
+
+/// A number that is either an integer or a float.
+///
+/// Operations on two integers result in an integer, operations that involve a float result in a float.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Integer(value) => value as f64,
+            Number::Float(value) => value,
+        }
+    }
+
+    fn to_value(self) -> Value {
+        match self {
+            Number::Integer(value) => Value::Integer(value),
+            Number::Float(value) => Value::Float(value),
+        }
+    }
+
+    fn is_zero(self) -> bool {
+        self.as_f64() == 0.0
+    }
+
+    fn apply<I, F>(self, other: Number, integer_operation: I, float_operation: F) -> Result<Number, SyntaxError>
+        where I: Fn(i64, i64) -> Option<i64>, F: Fn(f64, f64) -> f64 {
+        match (self, other) {
+            (Number::Integer(a), Number::Integer(b)) => integer_operation(a, b)
+                .map(Number::Integer)
+                .ok_or_else(|| overflow(&[Value::Integer(a), Value::Integer(b)])),
+            (a, b) => Ok(Number::Float(float_operation(a.as_f64(), b.as_f64()))),
+        }
+    }
+
+    fn compare(self, other: Number) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Integer(a), Number::Integer(b)) => Some(a.cmp(&b)),
+            (a, b) => a.as_f64().partial_cmp(&b.as_f64()),
+        }
+    }
+}
+
+fn number_of(value: &Value) -> Option<Number> {
+    match value {
+        Value::Integer(value) => Some(Number::Integer(*value)),
+        Value::Float(value) => Some(Number::Float(*value)),
+        Value::String(text) | Value::Safe(text) => text.parse::<i64>().map(Number::Integer).ok()
+            .or_else(|| text.parse::<f64>().map(Number::Float).ok()),
+        _ => None,
+    }
+}
+
+fn as_number_value(value: &Value) -> Result<Number, SyntaxError> {
+    number_of(value).ok_or_else(|| SyntaxError::new(EvaluationError::InvalidValues {
+        description: Some(format!("{} is not a number", value.type_name())),
+        values: vec![value.clone()],
+    }))
+}
+
+fn as_integer(value: &Value) -> Result<i64, SyntaxError> {
+    match as_number_value(value)? {
+        Number::Integer(value) => Ok(value),
+        Number::Float(_) => Err(SyntaxError::new(EvaluationError::InvalidValues {
+            description: Some("float is not an integer".to_string()),
+            values: vec![value.clone()],
+        })),
+    }
+}
+
+fn reduce<F>(parameters: &[Value], f: F) -> Result<Value, SyntaxError>
+    where F: Fn(Number, Number) -> Result<Number, SyntaxError> {
+    let mut numbers = parameters.iter().map(as_number_value);
+    let mut result = match numbers.next() {
+        Some(first) => first?,
+        None => return Ok(Value::Integer(0)),
+    };
+    for number in numbers {
+        result = f(result, number?)?;
+    }
+    Ok(result.to_value())
+}
+
+fn select(parameters: &[Value], usage: &str, wanted: Ordering) -> Result<Value, SyntaxError> {
+    let mut numbers = parameters.iter().map(as_number_value);
+    let mut result = match numbers.next() {
+        Some(first) => first?,
+        None => return Err(invalid_parameters(parameters, usage)),
+    };
+    for number in numbers {
+        let number = number?;
+        match number.compare(result) {
+            Some(ordering) if ordering == wanted => result = number,
+            Some(_) => {}
+            None => return Err(not_comparable(parameters)),
+        }
+    }
+    Ok(result.to_value())
 }
 
-pub fn reduce_numbers<F>(parameters: &[String], f: F) -> Result<String, SyntaxError>
-    where F: Fn(f64, f64) -> f64 {
-    let (index, numbers) = as_numbers(parameters);
+/// Rounds a float with the given function, the result is an integer if it fits in one.
+fn round_number(number: Number, round: fn(f64) -> f64) -> Value {
+    match number {
+        Number::Integer(value) => Value::Integer(value),
+        Number::Float(value) => {
+            let rounded = round(value);
+            float_to_integer(rounded).map(Value::Integer).unwrap_or(Value::Float(rounded))
+        }
+    }
+}
 
-    if let Result::Err(error) = numbers {
-        Err(SyntaxError::new(EvaluationError::InvalidValues {
-            description: Some(error.to_string()),
-            values: vec![parameters[index - 1].clone()],
-        })
-        )
+fn float_to_integer(value: f64) -> Option<i64> {
+    // i64::MAX as f64 is rounded up to 2^63, so it is out of range
+    if value.is_finite() && value >= i64::MIN as f64 && value < i64::MAX as f64 {
+        Some(value as i64)
     } else {
-        Ok(numbers.unwrap()
-            .into_iter()
-            .reduce(f)
-            .unwrap_or(0 as f64)
-            .to_string()
-        )
-    }
-}
-
-pub fn as_numbers(parameters: &[String]) -> (usize, Result<Vec<f64>, ParseFloatError>) {
-    let mut index: usize = 0;
-    let numbers: Result<Vec<f64>, ParseFloatError> = parameters.iter()
-        .map(|number| {
-            index += 1;
-            number.parse::<f64>()
-        })
-        .collect();
-
-    (index, numbers)
-}
\ No newline at end of file
+        None
+    }
+}
+
+fn division_by_zero(values: &[Value]) -> SyntaxError {
+    SyntaxError::new(EvaluationError::InvalidValues {
+        description: Some("division by zero".to_string()),
+        values: values.to_vec(),
+    })
+}
+
+fn overflow(values: &[Value]) -> SyntaxError {
+    SyntaxError::new(EvaluationError::InvalidValues {
+        description: Some("integer overflow".to_string()),
+        values: values.to_vec(),
+    })
+}
+
+fn not_comparable(values: &[Value]) -> SyntaxError {
+    SyntaxError::new(EvaluationError::InvalidValues {
+        description: Some("NaN cannot be compared".to_string()),
+        values: values.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rubble_templates_core::evaluator::{Context, EvaluationError, SyntaxError};
+    use rubble_templates_core::value::Value;
+    use crate::std_fun::math::{abs_function, clamp_function, divide_function, int_function, integer_divide_function, minus_function,
+                               modulo_function, multiply_function, plus_function, pow_function, round_function, sqrt_function};
+
+    fn text(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+
+    fn description(result: Result<Value, SyntaxError>) -> Option<String> {
+        match result.map_err(|error| error.description) {
+            Err(EvaluationError::InvalidValues { description, .. }) => description,
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn should_truncate_integer_division() {
+        let mut context = Context::empty();
+        let cases = [
+            (10, 2, 5),
+            (10, 3, 3),
+            (10, 4, 2),
+            (-7, 2, -3),
+            (7, -2, -3),
+            (i64::MIN, 1, i64::MIN),
+        ];
+
+        for (a, b, expected) in cases.iter() {
+            let result = divide_function(&[Value::Integer(*a), Value::Integer(*b)], &mut context);
+            assert_eq!(result, Ok(Value::Integer(*expected)), "{} / {}", a, b);
+        }
+        assert_eq!(divide_function(&[Value::Integer(10), Value::Float(4.0)], &mut context), Ok(Value::Float(2.5)));
+    }
+
+    #[test]
+    fn should_floor_integer_division() {
+        let mut context = Context::empty();
+        let cases = [
+            (10, 3, 3),
+            (-7, 2, -4),
+            (7, -2, -4),
+            (-7, -2, 3),
+            (-8, 2, -4),
+        ];
+
+        for (a, b, expected) in cases.iter() {
+            let result = integer_divide_function(&[Value::Integer(*a), Value::Integer(*b)], &mut context);
+            assert_eq!(result, Ok(Value::Integer(*expected)), "div {} {}", a, b);
+        }
+    }
+
+    #[test]
+    fn should_report_division_overflow() {
+        let mut context = Context::empty();
+        let parameters = [Value::Integer(i64::MIN), Value::Integer(-1)];
+
+        assert_eq!(description(divide_function(&parameters, &mut context)), Some("integer overflow".to_string()));
+        assert_eq!(description(integer_divide_function(&parameters, &mut context)), Some("integer overflow".to_string()));
+        assert_eq!(description(modulo_function(&parameters, &mut context)), Some("integer overflow".to_string()));
+    }
+
+    #[test]
+    fn should_keep_integer_precision() {
+        let mut context = Context::empty();
+        let id = text("9007199254740993");
+
+        assert_eq!(plus_function(&[id.clone(), Value::Integer(1)], &mut context), Ok(Value::Integer(9007199254740994)));
+        assert_eq!(minus_function(&[id, Value::Integer(9007199254740992)], &mut context), Ok(Value::Integer(1)));
+        assert_eq!(multiply_function(&[Value::Integer(3037000499), Value::Integer(3037000499)], &mut context),
+                   Ok(Value::Integer(9223372030926249001)));
+        assert_eq!(plus_function(&[Value::Integer(1), Value::Float(0.5)], &mut context), Ok(Value::Float(1.5)));
+    }
+
+    #[test]
+    fn should_reject_division_by_zero() {
+        let mut context = Context::empty();
+        let expected = Some("division by zero".to_string());
+
+        assert_eq!(description(divide_function(&[Value::Integer(10), Value::Integer(0)], &mut context)), expected);
+        assert_eq!(description(divide_function(&[Value::Float(1.5), Value::Float(0.0)], &mut context)), expected);
+        assert_eq!(description(divide_function(&[Value::Integer(10), Value::Float(-0.0)], &mut context)), expected);
+        assert_eq!(description(divide_function(&[Value::Integer(10), Value::Integer(2), Value::Integer(0)], &mut context)), expected);
+        assert_eq!(description(modulo_function(&[Value::Integer(7), Value::Integer(0)], &mut context)), expected);
+        assert_eq!(description(integer_divide_function(&[Value::Integer(7), Value::Integer(0)], &mut context)), expected);
+    }
+
+    #[test]
+    fn should_report_integer_overflow() {
+        let mut context = Context::empty();
+        let expected = Some("integer overflow".to_string());
+
+        assert_eq!(description(plus_function(&[Value::Integer(i64::MAX), Value::Integer(1)], &mut context)), expected);
+        assert_eq!(description(minus_function(&[Value::Integer(i64::MIN), Value::Integer(1)], &mut context)), expected);
+        assert_eq!(description(multiply_function(&[Value::Integer(4611686018427387904), Value::Integer(2)], &mut context)), expected);
+        assert_eq!(description(pow_function(&[Value::Integer(10), Value::Integer(19)], &mut context)), expected);
+        assert_eq!(description(abs_function(&[Value::Integer(i64::MIN)], &mut context)), expected);
+        assert_eq!(description(round_function(&[Value::Integer(i64::MAX), Value::Integer(-1)], &mut context)), expected);
+        assert_eq!(pow_function(&[Value::Integer(10), Value::Integer(18)], &mut context), Ok(Value::Integer(1_000_000_000_000_000_000)));
+    }
+
+    #[test]
+    fn should_reject_invalid_values() {
+        let mut context = Context::empty();
+
+        assert_eq!(description(integer_divide_function(&[Value::Float(7.5), Value::Integer(2)], &mut context)),
+                   Some("float is not an integer".to_string()));
+        assert_eq!(description(sqrt_function(&[Value::Integer(-1)], &mut context)),
+                   Some("square root of a negative number".to_string()));
+        assert_eq!(description(clamp_function(&[Value::Integer(1), Value::Integer(10), Value::Integer(0)], &mut context)),
+                   Some("min is greater than max".to_string()));
+        assert_eq!(description(int_function(&[text("abc")], &mut context)),
+                   Some("string is not a number".to_string()));
+        assert_eq!(description(int_function(&[Value::Float(1e19)], &mut context)),
+                   Some("10000000000000000000 cannot be converted to an integer".to_string()));
+    }
+
+    #[test]
+    fn should_round_floats_without_infinity() {
+        let mut context = Context::empty();
+        let cases = [
+            (2.25, 400, Value::Float(2.25)),
+            (2.25, 17, Value::Float(2.25)),
+            (1e300, 10, Value::Float(1e300)),
+            (1234.5, -400, Value::Integer(0)),
+            (1234.5, -2, Value::Integer(1200)),
+            (2.256, 2, Value::Float(2.26)),
+        ];
+
+        for (value, precision, expected) in cases.iter() {
+            let result = round_function(&[Value::Float(*value), Value::Integer(*precision)], &mut context);
+            assert_eq!(result.as_ref(), Ok(expected), "round {} {}", value, precision);
+        }
+    }
+
+    #[test]
+    fn should_round_integers_to_large_negative_precision() {
+        let mut context = Context::empty();
+        let round = |value: i64, precision: i64, context: &mut Context| round_function(&[Value::Integer(value), Value::Integer(precision)], context);
+
+        assert_eq!(round(4_999_999_999_999_999_999, -19, &mut context), Ok(Value::Integer(0)));
+        assert_eq!(round(-4_999_999_999_999_999_999, -19, &mut context), Ok(Value::Integer(0)));
+        assert_eq!(round(i64::MAX, -40, &mut context), Ok(Value::Integer(0)));
+        assert_eq!(round(1_250, -2, &mut context), Ok(Value::Integer(1_300)));
+        assert_eq!(round(-1_250, -2, &mut context), Ok(Value::Integer(-1_300)));
+        assert_eq!(description(round(5_000_000_000_000_000_000, -19, &mut context)), Some("integer overflow".to_string()));
+        assert_eq!(description(round(i64::MIN, -19, &mut context)), Some("integer overflow".to_string()));
+        assert_eq!(round(i64::MAX, -20, &mut context), Ok(Value::Integer(0)));
+    }
+}
@@ -3,11 +3,21 @@
 //! See [`std_fun`](rubble-templates::std_fun) or [`string_functions`] for more info.
 //!
 use std::collections::HashMap;
-use rubble_templates_core::evaluator::Function;
-use rubble_templates_core::functions::SimpleFunction;
+use std::convert::TryFrom;
+use rubble_templates_core::evaluator::{Function, Context, SyntaxError, EvaluationError};
+use rubble_templates_core::functions::{SimpleFunction, FunctionWithContext};
+use rubble_templates_core::value::Value;
+use crate::std_fun::logic::values_equal;
 
 pub const EMPTY_STRING: &str = "";
 
+/// The maximum length (in bytes) of text created by [`repeat`](repeat_function), [`pad_left`](pad_left_function),
+/// [`pad_right`](pad_right_function) and [`replace`](replace_function).
+///
+/// The count and the width come from the template, so longer results are rejected with an error
+/// instead of allocating arbitrary amounts of memory.
+pub const MAX_GENERATED_LENGTH: usize = 1024 * 1024;
+
 /// Provides a set of string related functions.
 ///
 /// Available functions:
@@ -15,12 +25,52 @@ pub const EMPTY_STRING: &str = "";
 /// * [`trim`](trim_function) - Trims parameters.
 /// * [`$}`](right_brackets_function) - Inserts "}}".
 /// * [`$quote`](right_brackets_function) - Inserts double qoutes (").
+/// * [`safe`](safe_function) or `raw` - Marks the text as safe, so it is not escaped by the compiler.
+/// * [`upper`](upper_function), [`lower`](lower_function) - Converts the text to upper or lower case.
+/// * [`capitalize`](capitalize_function) - Converts the first character to upper case.
+/// * [`replace`](replace_function) - Replaces all occurrences of a pattern.
+/// * [`split`](split_function) - Splits the text into a list.
+/// * [`join`](join_function) - Joins a list into text.
+/// * [`substring`](substring_function) - Takes a number of characters starting at an index.
+/// * [`slice`](slice_function) - Takes characters between two indexes, negative indexes count from the end.
+/// * [`length`](length_function) - Counts characters (or elements of a list or a map).
+/// * [`repeat`](repeat_function) - Repeats the text.
+/// * [`pad_left`](pad_left_function), [`pad_right`](pad_right_function) - Pads the text to a width.
+/// * [`contains`](contains_function) - Checks whether the text contains a pattern (or a list contains a value).
+/// * [`starts_with`](starts_with_function), [`ends_with`](ends_with_function) - Checks the beginning or the end of the text.
+/// * [`trim_start`](trim_start_function), [`trim_end`](trim_end_function) - Trims only one side of the parameters.
+/// * [`reverse`](reverse_function) - Reverses the text (or a list).
+///
+/// Indexes, lengths and widths are counted in characters, not bytes.
+/// Text created by `repeat`, `pad_left`, `pad_right` and `replace` cannot be longer than [MAX_GENERATED_LENGTH] bytes.
+///
+/// String literals can also contain `}}` and escaped quotes directly, eg. `{{ concat "}}" "\"" }}`.
 pub fn string_functions() -> HashMap<String, Box<dyn Function>> {
     let mut functions: HashMap<String, Box<dyn Function>> = HashMap::new();
     functions.insert("concat".to_string(), SimpleFunction::new(concat_function));
     functions.insert("trim".to_string(), SimpleFunction::new(trim_function));
     functions.insert("$}".to_string(), SimpleFunction::new(right_brackets_function));
     functions.insert("$quote".to_string(), SimpleFunction::new(quotes_function));
+    functions.insert("safe".to_string(), SimpleFunction::new(safe_function));
+    functions.insert("raw".to_string(), SimpleFunction::new(safe_function));
+    functions.insert("upper".to_string(), SimpleFunction::new(upper_function));
+    functions.insert("lower".to_string(), SimpleFunction::new(lower_function));
+    functions.insert("capitalize".to_string(), SimpleFunction::new(capitalize_function));
+    functions.insert("replace".to_string(), FunctionWithContext::new(replace_function));
+    functions.insert("split".to_string(), FunctionWithContext::new(split_function));
+    functions.insert("join".to_string(), FunctionWithContext::new(join_function));
+    functions.insert("substring".to_string(), FunctionWithContext::new(substring_function));
+    functions.insert("slice".to_string(), FunctionWithContext::new(slice_function));
+    functions.insert("length".to_string(), FunctionWithContext::new(length_function));
+    functions.insert("repeat".to_string(), FunctionWithContext::new(repeat_function));
+    functions.insert("pad_left".to_string(), FunctionWithContext::new(pad_left_function));
+    functions.insert("pad_right".to_string(), FunctionWithContext::new(pad_right_function));
+    functions.insert("contains".to_string(), FunctionWithContext::new(contains_function));
+    functions.insert("starts_with".to_string(), FunctionWithContext::new(starts_with_function));
+    functions.insert("ends_with".to_string(), FunctionWithContext::new(ends_with_function));
+    functions.insert("trim_start".to_string(), SimpleFunction::new(trim_start_function));
+    functions.insert("trim_end".to_string(), SimpleFunction::new(trim_end_function));
+    functions.insert("reverse".to_string(), FunctionWithContext::new(reverse_function));
     functions
 }
 
@@ -34,12 +84,12 @@ pub fn string_functions() -> HashMap<String, Box<dyn Function>> {
 /// ```text
 /// 1hello 3.14world!
 /// ```
-pub fn concat_function(parameters: &[String]) -> String {
+pub fn concat_function(parameters: &[Value]) -> Value {
     let mut result = EMPTY_STRING.to_string();
     parameters.iter().for_each(|param| {
-        result.push_str(param);
+        result.push_str(&param.to_string());
     });
-    result
+    Value::String(result)
 }
 
 /// Trims the parameters.
@@ -55,12 +105,12 @@ pub fn concat_function(parameters: &[String]) -> String {
 /// hello
 /// helloworldtext
 /// ```
-pub fn trim_function(parameters: &[String]) -> String {
+pub fn trim_function(parameters: &[Value]) -> Value {
     let mut result = EMPTY_STRING.to_string();
     parameters.iter().for_each(|param| {
-        result.push_str(param.trim());
+        result.push_str(param.to_string().trim());
     });
-    result
+    Value::String(result)
 }
 
 /// Inserts "}}". Ignores the parameters.
@@ -75,8 +125,8 @@ pub fn trim_function(parameters: &[String]) -> String {
 /// }}
 /// }}
 /// ```
-pub fn right_brackets_function(_: &[String]) -> String {
-    "}}".to_string()
+pub fn right_brackets_function(_: &[Value]) -> Value {
+    Value::String("}}".to_string())
 }
 
 /// Inserts ". Ignores the parameters.
@@ -91,6 +141,600 @@ pub fn right_brackets_function(_: &[String]) -> String {
 /// "
 /// "
 /// ```
-pub fn quotes_function(_: &[String]) -> String {
-    "\"".to_string()
-}
\ No newline at end of file
+pub fn quotes_function(_: &[Value]) -> Value {
+    Value::String("\"".to_string())
+}
+
+/// Concatenates the parameters and marks the result as [Value::Safe], so it is emitted without escaping.
+///
+/// Eg. with HTML escaping enabled and `link` set to `<a href="/">home</a>`:
+/// ```text
+/// {{ safe link }}
+/// {{ link }}
+/// ```
+/// Expected output:
+/// ```text
+/// <a href="/">home</a>
+/// &lt;a href=&quot;/&quot;&gt;home&lt;/a&gt;
+/// ```
+pub fn safe_function(parameters: &[Value]) -> Value {
+    let mut result = EMPTY_STRING.to_string();
+    parameters.iter().for_each(|param| {
+        result.push_str(&param.to_string());
+    });
+    Value::Safe(result)
+}
+
+/// Converts the parameters to upper case.
+/// If there is more than one parameter, then concatenates them.
+///
+/// Eg.
+/// ```text
+/// upper "hello " "żółw"
+/// ```
+/// Expected output:
+/// ```text
+/// HELLO ŻÓŁW
+/// ```
+pub fn upper_function(parameters: &[Value]) -> Value {
+    Value::String(concat_function(parameters).to_string().to_uppercase())
+}
+
+/// Converts the parameters to lower case.
+/// If there is more than one parameter, then concatenates them.
+///
+/// Eg.
+/// ```text
+/// lower "HELLO " "World"
+/// ```
+/// Expected output:
+/// ```text
+/// hello world
+/// ```
+pub fn lower_function(parameters: &[Value]) -> Value {
+    Value::String(concat_function(parameters).to_string().to_lowercase())
+}
+
+/// Converts the first character of the parameters to upper case, the rest of the text is left unchanged.
+/// If there is more than one parameter, then concatenates them.
+///
+/// Eg.
+/// ```text
+/// capitalize "hello World"
+/// capitalize "éclair"
+/// ```
+/// Expected output:
+/// ```text
+/// Hello World
+/// Éclair
+/// ```
+pub fn capitalize_function(parameters: &[Value]) -> Value {
+    let text = concat_function(parameters).to_string();
+    let mut chars = text.chars();
+    let result = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => text,
+    };
+    Value::String(result)
+}
+
+/// Replaces all occurrences of a pattern in the text.
+///
+/// Expects the text, the pattern and the replacement. An empty pattern matches before and after every character.
+/// If the result is longer than the text, it cannot be longer than [MAX_GENERATED_LENGTH] bytes.
+///
+/// Eg.
+/// ```text
+/// replace "a-b-c" "-" " + "
+/// replace "abc" "" "."
+/// ```
+/// Expected output:
+/// ```text
+/// a + b + c
+/// .a.b.c.
+/// ```
+pub fn replace_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let (text, pattern, replacement) = match parameters {
+        [text, pattern, replacement] => (text.to_string(), pattern.to_string(), replacement.to_string()),
+        _ => return Err(invalid_parameters(parameters, "replace text pattern replacement")),
+    };
+
+    // an empty pattern matches between all characters, so the result can be much longer than the text
+    let matches = if pattern.is_empty() {
+        text.chars().count() + 1
+    } else {
+        text.matches(&pattern).count()
+    };
+    let length = replacement.len().checked_mul(matches)
+        .and_then(|added| (text.len() - matches * pattern.len()).checked_add(added));
+    match length {
+        Some(length) if length <= MAX_GENERATED_LENGTH.max(text.len()) => Ok(Value::String(text.replace(&pattern, &replacement))),
+        _ => Err(too_long(parameters)),
+    }
+}
+
+/// Splits the text into a list of strings.
+///
+/// Splits on the given separator, or on whitespace if there is no separator.
+///
+/// Eg.
+/// ```text
+/// split "a,b,,c" ","
+/// split " a  b c "
+/// ```
+/// Expected output:
+/// ```text
+/// [a, b, , c]
+/// [a, b, c]
+/// ```
+pub fn split_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let parts: Vec<Value> = match parameters {
+        [text] => text.to_string().split_whitespace().map(Value::from).collect(),
+        [text, separator] => text.to_string().split(&separator.to_string()).map(Value::from).collect(),
+        _ => return Err(invalid_parameters(parameters, "split text [separator]")),
+    };
+    Ok(Value::List(parts))
+}
+
+/// Joins elements of a list into text, with an optional separator between them.
+///
+/// Eg. with `items` set to a list of `a`, `b` and `c`:
+/// ```text
+/// join items ", "
+/// join items
+/// ```
+/// Expected output:
+/// ```text
+/// a, b, c
+/// abc
+/// ```
+pub fn join_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let (values, separator) = match parameters {
+        [Value::List(values)] => (values, EMPTY_STRING.to_string()),
+        [Value::List(values), separator] => (values, separator.to_string()),
+        _ => return Err(invalid_parameters(parameters, "join list [separator]")),
+    };
+
+    let parts: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    Ok(Value::String(parts.join(&separator)))
+}
+
+/// Takes characters of the text starting at an index (from 0).
+///
+/// Takes the given number of characters, or all remaining characters if there is no length.
+/// Indexes and lengths out of the text are clamped.
+///
+/// Eg.
+/// ```text
+/// substring "żółw and turtle" 2 6
+/// substring "hello" 3
+/// ```
+/// Expected output:
+/// ```text
+/// łw and
+/// lo
+/// ```
+pub fn substring_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let (text, start, length) = match parameters {
+        [text, start] => (text.to_string(), as_count(start)?, None),
+        [text, start, length] => (text.to_string(), as_count(start)?, Some(as_count(length)?)),
+        _ => return Err(invalid_parameters(parameters, "substring text start [length]")),
+    };
+
+    let chars = text.chars().skip(start);
+    let result = match length {
+        Some(length) => chars.take(length).collect(),
+        None => chars.collect(),
+    };
+    Ok(Value::String(result))
+}
+
+/// Takes characters of the text between two indexes (the end is exclusive).
+///
+/// Negative indexes count from the end of the text, eg. `-1` is the last character.
+/// Without the end index, takes all remaining characters. Indexes out of the text are clamped.
+///
+/// Eg.
+/// ```text
+/// slice "hello world" 0 5
+/// slice "hello world" -5
+/// slice "hello world" 1 -1
+/// ```
+/// Expected output:
+/// ```text
+/// hello
+/// world
+/// ello worl
+/// ```
+pub fn slice_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let (text, start, end) = match parameters {
+        [text, start] => (text.to_string(), as_integer(start)?, None),
+        [text, start, end] => (text.to_string(), as_integer(start)?, Some(as_integer(end)?)),
+        _ => return Err(invalid_parameters(parameters, "slice text start [end]")),
+    };
+
+    let chars: Vec<char> = text.chars().collect();
+    let index = |position: i64| if position < 0 {
+        chars.len().saturating_sub(position.unsigned_abs() as usize)
+    } else {
+        (position as usize).min(chars.len())
+    };
+    let start = index(start);
+    let end = end.map(index).unwrap_or(chars.len());
+
+    let result = if start < end {
+        chars[start..end].iter().collect()
+    } else {
+        EMPTY_STRING.to_string()
+    };
+    Ok(Value::String(result))
+}
+
+/// Counts characters of the text, or elements of a list or a map.
+///
+/// Eg.
+/// ```text
+/// length "żółw"
+/// length (split "a b c")
+/// ```
+/// Expected output:
+/// ```text
+/// 4
+/// 3
+/// ```
+pub fn length_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let length = match parameters {
+        [Value::List(values)] => values.len(),
+        [Value::Map(values)] => values.len(),
+        [text] => text.to_string().chars().count(),
+        _ => return Err(invalid_parameters(parameters, "length value")),
+    };
+    Ok(Value::Integer(length as i64))
+}
+
+/// Repeats the text the given number of times.
+///
+/// The result cannot be longer than [MAX_GENERATED_LENGTH] bytes.
+///
+/// Eg.
+/// ```text
+/// repeat "ab" 3
+/// ```
+/// Expected output:
+/// ```text
+/// ababab
+/// ```
+pub fn repeat_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let (text, count) = match parameters {
+        [text, count] => (text.to_string(), as_count(count)?),
+        _ => return Err(invalid_parameters(parameters, "repeat text count")),
+    };
+
+    match text.len().checked_mul(count) {
+        Some(length) if length <= MAX_GENERATED_LENGTH => Ok(Value::String(text.repeat(count))),
+        _ => Err(too_long(parameters)),
+    }
+}
+
+/// Pads the text on the left to the given width (in characters).
+///
+/// Pads with spaces, or with the given fill character. Text that is already wide enough is left unchanged.
+/// The result cannot be longer than [MAX_GENERATED_LENGTH] bytes.
+///
+/// Eg.
+/// ```text
+/// pad_left 42 5 "0"
+/// pad_left "żółw" 6
+/// ```
+/// Expected output:
+/// ```text
+/// 00042
+///   żółw
+/// ```
+pub fn pad_left_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let (text, padding) = padding_of(parameters, "pad_left text width [fill]")?;
+    Ok(Value::String(padding + &text))
+}
+
+/// Pads the text on the right to the given width (in characters), see [`pad_left`](pad_left_function).
+///
+/// Eg.
+/// ```text
+/// pad_right "ab" 4 "."
+/// ```
+/// Expected output:
+/// ```text
+/// ab..
+/// ```
+pub fn pad_right_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    let (text, padding) = padding_of(parameters, "pad_right text width [fill]")?;
+    Ok(Value::String(text + &padding))
+}
+
+/// Checks whether the text contains a pattern, or whether a list contains a value.
+///
+/// Values in a list are compared like in [`==`](crate::std_fun::logic::equal_function).
+///
+/// Eg.
+/// ```text
+/// contains "hello world" "o w"
+/// contains (split "a b c") "d"
+/// ```
+/// Expected output:
+/// ```text
+/// true
+/// false
+/// ```
+pub fn contains_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    match parameters {
+        [Value::List(values), value] => Ok(Value::Bool(values.iter().any(|it| values_equal(it, value)))),
+        [text, pattern] => Ok(Value::Bool(text.to_string().contains(&pattern.to_string()))),
+        _ => Err(invalid_parameters(parameters, "contains text pattern")),
+    }
+}
+
+/// Checks whether the text starts with a pattern.
+///
+/// Eg.
+/// ```text
+/// starts_with "hello" "he"
+/// ```
+/// Expected output:
+/// ```text
+/// true
+/// ```
+pub fn starts_with_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    match parameters {
+        [text, pattern] => Ok(Value::Bool(text.to_string().starts_with(&pattern.to_string()))),
+        _ => Err(invalid_parameters(parameters, "starts_with text pattern")),
+    }
+}
+
+/// Checks whether the text ends with a pattern.
+///
+/// Eg.
+/// ```text
+/// ends_with "hello" "he"
+/// ```
+/// Expected output:
+/// ```text
+/// false
+/// ```
+pub fn ends_with_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    match parameters {
+        [text, pattern] => Ok(Value::Bool(text.to_string().ends_with(&pattern.to_string()))),
+        _ => Err(invalid_parameters(parameters, "ends_with text pattern")),
+    }
+}
+
+/// Trims whitespace at the start of the parameters.
+/// If there is more than one parameter, then concatenates them.
+///
+/// Eg.
+/// ```text
+/// trim_start "  hello" "  world"
+/// ```
+/// Expected output:
+/// ```text
+/// helloworld
+/// ```
+pub fn trim_start_function(parameters: &[Value]) -> Value {
+    let mut result = EMPTY_STRING.to_string();
+    parameters.iter().for_each(|param| {
+        result.push_str(param.to_string().trim_start());
+    });
+    Value::String(result)
+}
+
+/// Trims whitespace at the end of the parameters.
+/// If there is more than one parameter, then concatenates them.
+///
+/// Eg.
+/// ```text
+/// trim_end "hello  " "world  "
+/// ```
+/// Expected output:
+/// ```text
+/// helloworld
+/// ```
+pub fn trim_end_function(parameters: &[Value]) -> Value {
+    let mut result = EMPTY_STRING.to_string();
+    parameters.iter().for_each(|param| {
+        result.push_str(param.to_string().trim_end());
+    });
+    Value::String(result)
+}
+
+/// Reverses characters of the text, or elements of a list.
+///
+/// Eg.
+/// ```text
+/// reverse "żółw"
+/// reverse (split "a b c")
+/// ```
+/// Expected output:
+/// ```text
+/// włóż
+/// [c, b, a]
+/// ```
+pub fn reverse_function(parameters: &[Value], _context: &mut Context) -> Result<Value, SyntaxError> {
+    match parameters {
+        [Value::List(values)] => Ok(Value::List(values.iter().rev().cloned().collect())),
+        [text] => Ok(Value::String(text.to_string().chars().rev().collect())),
+        _ => Err(invalid_parameters(parameters, "reverse value")),
+    }
+}
+
+/// Reads the text and the padding for [`pad_left`](pad_left_function) and [`pad_right`](pad_right_function).
+fn padding_of(parameters: &[Value], usage: &str) -> Result<(String, String), SyntaxError> {
+    let (text, width, fill) = match parameters {
+        [text, width] => (text.to_string(), as_count(width)?, ' '),
+        [text, width, fill] => {
+            let fill = fill.to_string();
+            let mut chars = fill.chars();
+            match (chars.next(), chars.next()) {
+                (Some(fill), None) => (text.to_string(), as_count(width)?, fill),
+                _ => return Err(invalid_parameters(parameters, usage)),
+            }
+        }
+        _ => return Err(invalid_parameters(parameters, usage)),
+    };
+
+    let padding = width.saturating_sub(text.chars().count());
+    match padding.checked_mul(fill.len_utf8()).and_then(|length| length.checked_add(text.len())) {
+        Some(length) if length <= MAX_GENERATED_LENGTH => Ok((text, fill.to_string().repeat(padding))),
+        _ => Err(too_long(parameters)),
+    }
+}
+
+fn too_long(parameters: &[Value]) -> SyntaxError {
+    SyntaxError::new(EvaluationError::InvalidValues {
+        description: Some(format!("result would be longer than {} bytes", MAX_GENERATED_LENGTH)),
+        values: parameters.to_vec(),
+    })
+}
+
+pub(crate) fn invalid_parameters(parameters: &[Value], usage: &str) -> SyntaxError {
+    SyntaxError::new(EvaluationError::InvalidValues {
+        description: Some(format!("expected `{}`", usage)),
+        values: parameters.to_vec(),
+    })
+}
+
+/// Converts a value to an integer, integers passed as text are parsed.
+fn as_integer(value: &Value) -> Result<i64, SyntaxError> {
+    let integer = match value {
+        Value::Integer(value) => Some(*value),
+        Value::String(text) | Value::Safe(text) => text.parse::<i64>().ok(),
+        _ => None,
+    };
+
+    integer.ok_or_else(|| SyntaxError::new(EvaluationError::InvalidValues {
+        description: Some(format!("{} is not an integer", value.type_name())),
+        values: vec![value.clone()],
+    }))
+}
+
+/// Converts a value to a non-negative integer, eg. an index or a length.
+fn as_count(value: &Value) -> Result<usize, SyntaxError> {
+    usize::try_from(as_integer(value)?).map_err(|_| SyntaxError::new(EvaluationError::InvalidValues {
+        description: Some("expected a non-negative integer".to_string()),
+        values: vec![value.clone()],
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use rubble_templates_core::evaluator::{Context, EvaluationError, SyntaxError};
+    use rubble_templates_core::value::Value;
+    use crate::std_fun::strings::{join_function, pad_left_function, pad_right_function, repeat_function, replace_function, slice_function, substring_function, MAX_GENERATED_LENGTH};
+
+    fn text(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+
+    fn description(result: Result<Value, SyntaxError>) -> Option<String> {
+        match result.map_err(|error| error.description) {
+            Err(EvaluationError::InvalidValues { description, .. }) => description,
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn should_limit_repeated_text() {
+        let mut context = Context::empty();
+        let expected = Some(format!("result would be longer than {} bytes", MAX_GENERATED_LENGTH));
+        let max_count = Value::Integer((MAX_GENERATED_LENGTH / 2) as i64);
+
+        let result = repeat_function(&[text("ab"), max_count], &mut context);
+        assert_eq!(result.map(|value| value.to_string().len()), Ok(MAX_GENERATED_LENGTH));
+
+        for count in &[(MAX_GENERATED_LENGTH / 2 + 1) as i64, i64::MAX] {
+            let result = repeat_function(&[text("ab"), Value::Integer(*count)], &mut context);
+            assert_eq!(description(result), expected, "{}", count);
+        }
+        assert_eq!(repeat_function(&[text(""), Value::Integer(i64::MAX)], &mut context), Ok(text("")));
+    }
+
+    #[test]
+    fn should_limit_padding() {
+        let mut context = Context::empty();
+        let expected = Some(format!("result would be longer than {} bytes", MAX_GENERATED_LENGTH));
+
+        let result = pad_left_function(&[text("ab"), Value::Integer(MAX_GENERATED_LENGTH as i64), text("0")], &mut context);
+        assert_eq!(result.map(|value| value.to_string().len()), Ok(MAX_GENERATED_LENGTH));
+
+        let result = pad_right_function(&[text("ab"), Value::Integer(MAX_GENERATED_LENGTH as i64), text("ż")], &mut context);
+        assert_eq!(description(result), expected);
+        let result = pad_left_function(&[text("ab"), Value::Integer(i64::MAX)], &mut context);
+        assert_eq!(description(result), expected);
+        assert_eq!(pad_left_function(&[text("żółw"), Value::Integer(6)], &mut context), Ok(text("  żółw")));
+    }
+
+    #[test]
+    fn should_clamp_indexes_out_of_text() {
+        let mut context = Context::empty();
+        let cases = [
+            (vec![Value::Integer(2), Value::Integer(100)], "łw"),
+            (vec![Value::Integer(100)], ""),
+            (vec![text("1"), Value::Integer(0)], ""),
+        ];
+
+        for (indexes, expected) in cases.iter() {
+            let parameters: Vec<Value> = std::iter::once(text("żółw")).chain(indexes.iter().cloned()).collect();
+            assert_eq!(substring_function(&parameters, &mut context), Ok(text(expected)), "{:?}", indexes);
+        }
+    }
+
+    #[test]
+    fn should_slice_with_negative_indexes() {
+        let mut context = Context::empty();
+        let cases = [
+            (vec![Value::Integer(-2)], "łw"),
+            (vec![Value::Integer(1), Value::Integer(-1)], "ół"),
+            (vec![Value::Integer(i64::MIN), Value::Integer(i64::MAX)], "żółw"),
+            (vec![Value::Integer(3), Value::Integer(1)], ""),
+            (vec![Value::Integer(-1), Value::Integer(-3)], ""),
+        ];
+
+        for (indexes, expected) in cases.iter() {
+            let parameters: Vec<Value> = std::iter::once(text("żółw")).chain(indexes.iter().cloned()).collect();
+            assert_eq!(slice_function(&parameters, &mut context), Ok(text(expected)), "{:?}", indexes);
+        }
+    }
+
+    #[test]
+    fn should_reject_invalid_parameters() {
+        let mut context = Context::empty();
+
+        assert_eq!(description(repeat_function(&[text("a"), Value::Integer(-1)], &mut context)),
+                   Some("expected a non-negative integer".to_string()));
+        assert_eq!(description(repeat_function(&[text("a"), Value::Float(2.0)], &mut context)),
+                   Some("float is not an integer".to_string()));
+        assert_eq!(description(substring_function(&[text("abc"), text("x")], &mut context)),
+                   Some("string is not an integer".to_string()));
+        assert_eq!(description(join_function(&[text("a"), text(",")], &mut context)),
+                   Some("expected `join list [separator]`".to_string()));
+        assert_eq!(description(pad_left_function(&[text("a"), Value::Integer(3), text("ab")], &mut context)),
+                   Some("expected `pad_left text width [fill]`".to_string()));
+        assert_eq!(description(pad_right_function(&[text("a"), Value::Integer(3), text("")], &mut context)),
+                   Some("expected `pad_right text width [fill]`".to_string()));
+    }
+
+    #[test]
+    fn should_limit_replaced_text() {
+        let mut context = Context::empty();
+        let expected = Some(format!("result would be longer than {} bytes", MAX_GENERATED_LENGTH));
+        let big = text(&"x".repeat(MAX_GENERATED_LENGTH / 4));
+
+        assert_eq!(replace_function(&[text("abc"), text(""), text(".")], &mut context), Ok(text(".a.b.c.")));
+        assert_eq!(description(replace_function(&[text("abcd"), text(""), big.clone()], &mut context)), expected);
+        assert_eq!(description(replace_function(&[text("a-b-c-d-e"), text("-"), big.clone()], &mut context)), expected);
+        let result = replace_function(&[text("a-b-c"), text("-"), big.clone()], &mut context);
+        assert_eq!(result.map(|value| value.to_string().len()), Ok(MAX_GENERATED_LENGTH / 2 + 3));
+
+        let long_text = text(&"ab".repeat(MAX_GENERATED_LENGTH));
+        let result = replace_function(&[long_text, text("ab"), text("c")], &mut context);
+        assert_eq!(result.map(|value| value.to_string().len()), Ok(MAX_GENERATED_LENGTH));
+    }
+}